@@ -0,0 +1,263 @@
+use std::{
+    io,
+    io::{ BufRead, Write },
+    thread,
+};
+
+use crate::{
+    game::Board,
+    smart_solver::{ self, SearchState },
+    solver::{ Budget, SolverStats },
+};
+
+// lines read before handing them out to the threads
+const CHUNK_SIZE: usize = 4096;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum BatchMode {
+    /// Print the solution of every uniquely solvable puzzle
+    #[default]
+    Solve,
+    /// Only tell whether every puzzle has a unique solution
+    Check,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BatchOptions {
+    pub mode: BatchMode,
+    pub threads: usize,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        BatchOptions {
+            mode: BatchMode::default(),
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+        }
+    }
+}
+
+/// How the puzzles of a batch turned out.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BatchSummary {
+    pub puzzles: u64,
+    pub unique: u64,
+    pub multiple: u64,
+    pub no_solution: u64,
+    pub invalid: u64,
+    pub gave_up: u64,
+}
+
+impl BatchSummary {
+    fn merge(&mut self, other: &BatchSummary) {
+        self.puzzles += other.puzzles;
+        self.unique += other.unique;
+        self.multiple += other.multiple;
+        self.no_solution += other.no_solution;
+        self.invalid += other.invalid;
+        self.gave_up += other.gave_up;
+    }
+}
+
+/// Solves one line and writes its result line to `out`.
+fn process_line(line: &str, mode: BatchMode, budget: &Budget, out: &mut Vec<u8>, summary: &mut BatchSummary) {
+    summary.puzzles += 1;
+    let board: Board = match line.parse() {
+        Ok(board) => board,
+        Err(e) => {
+            summary.invalid += 1;
+            writeln!(out, "invalid: {}", e).unwrap();
+            return;
+        },
+    };
+
+    // searched for two solutions right on the state, so that no
+    // solution lists are allocated
+    let mut count = 0;
+    let mut first = None;
+    let result = match SearchState::from_board(&board) {
+        Some(state) => smart_solver::search(state, 0, budget, &mut SolverStats::default(), &mut |state| {
+            count += 1;
+            if count == 1 { first = Some(state.filled_board(&board)); }
+            count < 2
+        }),
+        None => Ok(true),
+    };
+
+    match (result, count, first) {
+        (Err(_), _, _) => {
+            summary.gave_up += 1;
+            writeln!(out, "gave up").unwrap();
+        },
+        (_, 0, _) => {
+            summary.no_solution += 1;
+            writeln!(out, "none").unwrap();
+        },
+        (_, 1, Some(solution)) => {
+            summary.unique += 1;
+            match mode {
+                BatchMode::Solve => writeln!(out, "{}", solution).unwrap(),
+                BatchMode::Check => writeln!(out, "unique").unwrap(),
+            }
+        },
+        _ => {
+            summary.multiple += 1;
+            writeln!(out, "multiple").unwrap();
+        },
+    }
+}
+
+/// Reads one puzzle per line from `input` and writes one result line per
+/// input line to `output`, in the same order: the solution (or `unique`
+/// when only checking), `none`, `multiple`, `gave up` once the puzzle's
+/// budget has run out, or `invalid: ...` for lines that aren't puzzles.
+///
+/// `budget` is called for every puzzle, so that limits apply to each one
+/// on its own; budgets sharing a cancel flag still stop all together.
+///
+/// Lines are read in chunks that the threads split between them; the
+/// line and output buffers are reused from one chunk to the next.
+pub fn run<R: BufRead, W: Write, B: Fn() -> Budget + Sync>(
+    mut input: R,
+    mut output: W,
+    options: &BatchOptions,
+    budget: B,
+) -> io::Result<BatchSummary> {
+    let threads = options.threads.max(1);
+    let mut lines = vec![String::new(); CHUNK_SIZE];
+    let mut outputs = vec![Vec::new(); threads];
+    let mut summary = BatchSummary::default();
+
+    loop {
+        let mut len = 0;
+        while len < CHUNK_SIZE {
+            lines[len].clear();
+            if input.read_line(&mut lines[len])? == 0 { break; }
+            len += 1;
+        }
+        if len == 0 { break; }
+
+        let per_thread = len.div_ceil(threads);
+        let budget = &budget;
+        thread::scope(|scope| {
+            let handles: Vec<_> = lines[..len].chunks(per_thread)
+                .zip(outputs.iter_mut())
+                .map(|(part, out)| scope.spawn(move || {
+                    let mut summary = BatchSummary::default();
+                    out.clear();
+                    for line in part {
+                        process_line(line, options.mode, &budget(), out, &mut summary);
+                    }
+                    summary
+                }))
+                .collect();
+            for handle in handles {
+                summary.merge(&handle.join().unwrap());
+            }
+        });
+
+        for out in outputs.iter().take(len.div_ceil(per_thread)) {
+            output.write_all(out)?;
+        }
+        if len < CHUNK_SIZE { break; }
+    }
+
+    output.flush()?;
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod batch_tests {
+    use super::*;
+    use std::sync::{ Arc, atomic::AtomicBool };
+
+    const INPUT: &str = "\
+53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79
+.7.415369691783542435926781763258194.5.194637149637258387542916516879423924361875
+11...............................................................................
+not a puzzle
+000000010400000000020000000000050407008000300001090000300400200050100000000806000
+";
+
+    fn run_on(input: &str, options: &BatchOptions, budget: impl Fn() -> Budget + Sync) -> (Vec<String>, BatchSummary) {
+        let mut output = Vec::new();
+        let summary = run(input.as_bytes(), &mut output, options, budget).unwrap();
+        let lines = String::from_utf8(output).unwrap().lines().map(String::from).collect();
+        (lines, summary)
+    }
+
+    #[test]
+    fn test_one_result_per_line() {
+        let options = BatchOptions { mode: BatchMode::Solve, threads: 2 };
+        let (lines, summary) = run_on(INPUT, &options, Budget::unlimited);
+
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], "534678912672195348198342567859761423426853791713924856961537284287419635345286179");
+        assert_eq!(lines[1], "multiple");
+        assert_eq!(lines[2], "none");
+        assert!(lines[3].starts_with("invalid: "));
+        assert_eq!(lines[4].len(), 81);
+        assert_eq!(summary, BatchSummary {
+            puzzles: 5,
+            unique: 2,
+            multiple: 1,
+            no_solution: 1,
+            invalid: 1,
+            gave_up: 0,
+        });
+
+        let options = BatchOptions { mode: BatchMode::Check, threads: 1 };
+        let (lines, _) = run_on(INPUT, &options, Budget::unlimited);
+        assert_eq!(lines[0], "unique");
+        assert_eq!(lines[4], "unique");
+    }
+
+    #[test]
+    fn test_order_kept_across_chunks_and_threads() {
+        let puzzles = [
+            "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79",
+            "11...............................................................................",
+        ];
+        let input: String = (0..CHUNK_SIZE + 7)
+            .map(|i| format!("{}\n", puzzles[i % 3 % 2]))
+            .collect();
+        let options = BatchOptions { mode: BatchMode::Check, threads: 3 };
+        let (lines, summary) = run_on(&input, &options, Budget::unlimited);
+
+        assert_eq!(lines.len(), CHUNK_SIZE + 7);
+        for (i, line) in lines.iter().enumerate() {
+            assert_eq!(line, if i % 3 % 2 == 0 { "unique" } else { "none" });
+        }
+        assert_eq!(summary.puzzles, (CHUNK_SIZE + 7) as u64);
+    }
+
+    #[test]
+    fn test_gave_up() {
+        let cancelled = Arc::new(AtomicBool::new(true));
+        let budget = || Budget::unlimited().with_cancel_flag(cancelled.clone());
+        let (lines, summary) = run_on(INPUT, &BatchOptions::default(), budget);
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], "gave up");
+        // contradictions show before any searching
+        assert_eq!(lines[2], "none");
+        assert!(lines[3].starts_with("invalid: "));
+        assert_eq!(summary.gave_up, 3);
+    }
+
+    #[test]
+    fn test_limits_per_puzzle() {
+        let puzzle = "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79";
+        let budget = Budget::unlimited().with_max_nodes(1_000);
+        assert_eq!(smart_solver::solutions(&puzzle.parse().unwrap(), Some(2)).within(&budget).count(), 1);
+        let nodes = budget.nodes_spent();
+        assert!(nodes > 0);
+
+        // far more nodes in all than one puzzle's limit
+        let input = format!("{}\n", puzzle).repeat(50);
+        let options = BatchOptions { mode: BatchMode::Check, threads: 2 };
+        let (lines, summary) = run_on(&input, &options, || Budget::unlimited().with_max_nodes(4 * nodes));
+        assert!(lines.iter().all(|line| line == "unique"));
+        assert_eq!(summary.unique, 50);
+        assert_eq!(summary.gave_up, 0);
+    }
+}
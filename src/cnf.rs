@@ -0,0 +1,288 @@
+use std::{
+    error::Error,
+    fmt,
+    io,
+    io::Write,
+};
+
+use crate::{
+    evaluator,
+    game,
+    game::{ Board, Cell, Digit },
+    utilities,
+};
+
+pub const NUM_VARIABLES: usize = 729;
+
+/// DIMACS variable (1-based) that is true iff (row, col) holds `digit`.
+pub fn variable(row: usize, col: usize, digit: Digit) -> i32 {
+    (row * 81 + col * 9 + digit as usize + 1) as i32
+}
+
+fn variable_coords(var: i32) -> (usize, usize, Digit) {
+    let i = var as usize - 1;
+    (i / 81, (i / 9) % 9, game::LEGAL_VALUES[i % 9])
+}
+
+// the cells of every row, column and square
+fn units() -> impl Iterator<Item = [(usize, usize); 9]> {
+    (0..9).map(|r| std::array::from_fn(|c| (r, c)))
+        .chain((0..9).map(|c| std::array::from_fn(|r| (r, c))))
+        .chain((0..9).map(|s| {
+            let (u, l) = utilities::square_limits_from_square(s);
+            std::array::from_fn(|k| (u + k / 3, l + k % 3))
+        }))
+}
+
+/// The sudoku rules plus one unit clause per filled cell: every cell
+/// holds exactly one digit and every row, column and square holds every
+/// digit exactly once, "at most once" being spelled out pairwise.
+pub fn clauses(board: &Board) -> Vec<Vec<i32>> {
+    let mut clauses = Vec::new();
+
+    for r in 0..9 {
+        for c in 0..9 {
+            clauses.push(game::LEGAL_VALUES.map(|d| variable(r, c, d)).to_vec());
+            for (i, &d) in game::LEGAL_VALUES.iter().enumerate() {
+                for &e in game::LEGAL_VALUES[i + 1..].iter() {
+                    clauses.push(vec![-variable(r, c, d), -variable(r, c, e)]);
+                }
+            }
+        }
+    }
+
+    for unit in units() {
+        for d in game::LEGAL_VALUES {
+            clauses.push(unit.map(|(r, c)| variable(r, c, d)).to_vec());
+            for (i, &(r, c)) in unit.iter().enumerate() {
+                for &(s, t) in unit[i + 1..].iter() {
+                    clauses.push(vec![-variable(r, c, d), -variable(s, t, d)]);
+                }
+            }
+        }
+    }
+
+    for r in 0..9 {
+        for c in 0..9 {
+            if let Some(x) = board.at(r, c).digit() {
+                clauses.push(vec![variable(r, c, x)]);
+            }
+        }
+    }
+
+    clauses
+}
+
+/// Writes the formula of `board` in DIMACS CNF, with the puzzle itself
+/// in a comment line for reference.
+pub fn write_dimacs<W: Write>(board: &Board, out: &mut W) -> io::Result<()> {
+    let clauses = clauses(board);
+    writeln!(out, "c ku sudoku {}", board)?;
+    writeln!(out, "p cnf {} {}", NUM_VARIABLES, clauses.len())?;
+    for clause in clauses {
+        for lit in clause {
+            write!(out, "{} ", lit)?;
+        }
+        writeln!(out, "0")?;
+    }
+    Ok(())
+}
+
+pub fn to_dimacs(board: &Board) -> String {
+    let mut out = Vec::new();
+    write_dimacs(board, &mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ModelError {
+    /// The SAT solver found the formula unsatisfiable
+    Unsatisfiable,
+    InvalidLiteral(String),
+    /// No digit is true in the cell
+    MissingDigit(usize, usize),
+    /// Several digits are true in the cell
+    SeveralDigits(usize, usize),
+    /// The cell is filled with another digit on the board
+    Contradicts(usize, usize),
+    /// A digit is repeated in a row, column or square
+    BreaksRules,
+}
+
+impl fmt::Display for ModelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModelError::Unsatisfiable =>
+                write!(f, "the formula is unsatisfiable"),
+            ModelError::InvalidLiteral(s) =>
+                write!(f, "invalid literal '{}'", s),
+            ModelError::MissingDigit(r, c) =>
+                write!(f, "no digit assigned to {}", utilities::cell_name(*r, *c)),
+            ModelError::SeveralDigits(r, c) =>
+                write!(f, "several digits assigned to {}", utilities::cell_name(*r, *c)),
+            ModelError::Contradicts(r, c) =>
+                write!(f, "the model contradicts the board at {}", utilities::cell_name(*r, *c)),
+            ModelError::BreaksRules =>
+                write!(f, "the model repeats a digit in a row, column or square"),
+        }
+    }
+}
+
+impl Error for ModelError {}
+
+/// Reads a SAT solver's model of the formula of `board` back into the
+/// solved board, with the empty cells filled as non-givens. Models that
+/// don't make a valid solution are rejected, so that a faulty solver
+/// can't pass one off as solved.
+///
+/// Both the competition format (`s ...` status and `v ...` value lines)
+/// and MiniSat's result file (`SAT` followed by the literals) are
+/// understood; comment lines are skipped and variables left out of the
+/// model count as false.
+pub fn from_model(board: &Board, model: &str) -> Result<Board, ModelError> {
+    let mut digits: [[Option<Digit>; 9]; 9] = [[None; 9]; 9];
+
+    for line in model.lines().map(str::trim) {
+        let literals = match line.split_once(' ').map_or((line, ""), |split| split) {
+            ("c", _) | ("s", "SATISFIABLE") | ("SAT", _) => continue,
+            ("s", _) | ("UNSAT", _) => return Err(ModelError::Unsatisfiable),
+            ("v", rest) => rest,
+            _ => line,
+        };
+        for token in literals.split_whitespace() {
+            let lit: i32 = token.parse()
+                .map_err(|_| ModelError::InvalidLiteral(token.to_string()))?;
+            if lit.unsigned_abs() as usize > NUM_VARIABLES {
+                return Err(ModelError::InvalidLiteral(token.to_string()));
+            }
+            if lit <= 0 { continue; }
+
+            let (r, c, d) = variable_coords(lit);
+            if digits[r][c].is_some_and(|x| x != d) {
+                return Err(ModelError::SeveralDigits(r, c));
+            }
+            digits[r][c] = Some(d);
+        }
+    }
+
+    let mut solution = *board;
+    for (r, row) in digits.iter().enumerate() {
+        for (c, digit) in row.iter().enumerate() {
+            let Some(x) = *digit else { return Err(ModelError::MissingDigit(r, c)); };
+            match board.at(r, c) {
+                Cell::Empty => solution.set(r, c, Cell::NonGiven(x)),
+                cell if cell.digit() != Some(x) => return Err(ModelError::Contradicts(r, c)),
+                _ => {},
+            }
+        }
+    }
+    if !evaluator::evaluate(&solution) { return Err(ModelError::BreaksRules); }
+    Ok(solution)
+}
+
+#[cfg(test)]
+mod cnf_tests {
+    use super::*;
+
+    const SOLUTION: &str = "534678912672195348198342567859761423426853791713924856961537284287419635345286179";
+    const PUZZLE: &str = "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79";
+
+    // the complete assignment described by a filled board
+    fn model_of(solution: &Board) -> Vec<i32> {
+        (1..=NUM_VARIABLES as i32)
+            .map(|var| {
+                let (r, c, d) = variable_coords(var);
+                if solution.at(r, c).digit() == Some(d) { var } else { -var }
+            })
+            .collect()
+    }
+
+    fn satisfies(clauses: &[Vec<i32>], model: &[i32]) -> bool {
+        clauses.iter().all(|clause| clause.iter().any(|lit| model[lit.unsigned_abs() as usize - 1] == *lit))
+    }
+
+    #[test]
+    fn test_variables() {
+        assert_eq!(variable(0, 0, Digit::D1), 1);
+        assert_eq!(variable(8, 8, Digit::D9), NUM_VARIABLES as i32);
+        for var in 1..=NUM_VARIABLES as i32 {
+            let (r, c, d) = variable_coords(var);
+            assert_eq!(variable(r, c, d), var);
+        }
+    }
+
+    #[test]
+    fn test_encoding() {
+        let puzzle: Board = PUZZLE.parse().unwrap();
+        let clauses = clauses(&puzzle);
+        // 81 cells and 243 unit-digit pairs, each with 1 + 36 clauses
+        assert_eq!(clauses.len(), 324 * 37 + 30);
+
+        let solution: Board = SOLUTION.parse().unwrap();
+        assert!(satisfies(&clauses, &model_of(&solution)));
+
+        // swapping two digits in a row breaks the columns
+        let mut wrong = solution;
+        wrong.set(8, 0, solution.at(8, 1));
+        wrong.set(8, 1, solution.at(8, 0));
+        assert!(!satisfies(&clauses, &model_of(&wrong)));
+
+        // a valid grid that doesn't fit the givens
+        let other: Board = "872415369691783542435926781763258194258194637149637258387542916516879423924361875"
+            .parse().unwrap();
+        assert!(!satisfies(&clauses, &model_of(&other)));
+
+        let dimacs = to_dimacs(&puzzle);
+        let mut lines = dimacs.lines();
+        assert_eq!(lines.next(), Some(format!("c ku sudoku {}", puzzle).as_str()));
+        assert_eq!(lines.next(), Some(format!("p cnf 729 {}", clauses.len()).as_str()));
+        assert_eq!(lines.count(), clauses.len());
+    }
+
+    #[test]
+    fn test_reading_models() {
+        let puzzle: Board = PUZZLE.parse().unwrap();
+        let solution: Board = SOLUTION.parse().unwrap();
+        let literals: Vec<String> = model_of(&solution).iter().map(i32::to_string).collect();
+
+        let competition = format!("c some solver\ns SATISFIABLE\nv {}\nv {} 0\n",
+            literals[..400].join(" "), literals[400..].join(" "));
+        let minisat = format!("SAT\n{} 0\n", literals.join(" "));
+        for model in [competition, minisat] {
+            let board = from_model(&puzzle, &model).unwrap();
+            assert!(evaluator::evaluate(&board));
+            assert_eq!(board.to_string(), SOLUTION);
+            assert_eq!(board.at(0, 0), Cell::Given(Digit::D5));
+            assert_eq!(board.at(0, 2), Cell::NonGiven(Digit::D4));
+        }
+
+        assert_eq!(from_model(&puzzle, "s UNSATISFIABLE\n").err(), Some(ModelError::Unsatisfiable));
+        assert_eq!(from_model(&puzzle, "UNSAT\n").err(), Some(ModelError::Unsatisfiable));
+        assert_eq!(from_model(&puzzle, "v 1 x 0\n").err(), Some(ModelError::InvalidLiteral("x".to_string())));
+        assert_eq!(from_model(&puzzle, "v 1 2 0\n").err(), Some(ModelError::SeveralDigits(0, 0)));
+        assert_eq!(from_model(&puzzle, "v 5 0\n").err(), Some(ModelError::MissingDigit(0, 1)));
+
+        let positives: Vec<String> = model_of(&solution).iter()
+            .filter(|&&lit| lit > 0)
+            .map(i32::to_string)
+            .collect();
+        let other: Board = "872415369691783542435926781763258194258194637149637258387542916516879423924361875"
+            .parse().unwrap();
+        assert_eq!(
+            from_model(&other, &format!("v {} 0", positives.join(" "))).err(),
+            Some(ModelError::Contradicts(0, 0))
+        );
+
+        // a second 5 in the first row, in place of its 3
+        let mut wrong = solution;
+        wrong.set(0, 1, Cell::Given(Digit::D5));
+        let positives: Vec<String> = model_of(&wrong).iter()
+            .filter(|&&lit| lit > 0)
+            .map(i32::to_string)
+            .collect();
+        assert_eq!(
+            from_model(&Board::zeroed(), &format!("v {} 0", positives.join(" "))).err(),
+            Some(ModelError::BreaksRules)
+        );
+    }
+}
@@ -0,0 +1,399 @@
+use crate::{
+    game,
+    game::{ Board, Cell },
+    solver,
+    solver::{ Budget, BudgetExhausted, Solver, SolverStats },
+};
+
+// Exact cover columns, 81 each:
+//   0..81    cell (r, c) holds a digit
+//   81..162  row r holds digit d
+//   162..243 column c holds digit d
+//   243..324 square s holds digit d
+const NUM_COLUMNS: usize = 324;
+const NUM_ROWS: usize = 729;
+const ROOT: usize = 0;
+
+fn row_coords(row: usize) -> (usize, usize, usize) {
+    (row / 81, (row / 9) % 9, row % 9)
+}
+
+fn row_columns(row: usize) -> [usize; 4] {
+    let (r, c, d) = row_coords(row);
+    let s = (r / 3) * 3 + c / 3;
+    [r * 9 + c, 81 + r * 9 + d, 162 + c * 9 + d, 243 + s * 9 + d]
+}
+
+/// Knuth's Dancing Links over the 324-column sudoku exact cover matrix.
+///
+/// Nodes `1..=324` are column headers (node `0` is the root), the rest are
+/// the four nodes of every candidate row.
+pub struct DancingLinks {
+    left: Vec<usize>,
+    right: Vec<usize>,
+    up: Vec<usize>,
+    down: Vec<usize>,
+    column: Vec<usize>,
+    row: Vec<usize>,
+    size: [usize; NUM_COLUMNS + 1],
+    board: Board,
+    partial: Vec<usize>,
+    contradiction: bool,
+}
+
+impl DancingLinks {
+    pub fn new(board: &Board) -> Self {
+        let capacity = NUM_COLUMNS + 1 + NUM_ROWS * 4;
+        let mut dlx = DancingLinks {
+            left: Vec::with_capacity(capacity),
+            right: Vec::with_capacity(capacity),
+            up: Vec::with_capacity(capacity),
+            down: Vec::with_capacity(capacity),
+            column: Vec::with_capacity(capacity),
+            row: Vec::with_capacity(capacity),
+            size: [0; NUM_COLUMNS + 1],
+            board: *board,
+            partial: Vec::with_capacity(81),
+            contradiction: false,
+        };
+
+        // root and column headers in one circular list
+        for i in 0..=NUM_COLUMNS {
+            dlx.left.push(if i == 0 { NUM_COLUMNS } else { i - 1 });
+            dlx.right.push(if i == NUM_COLUMNS { 0 } else { i + 1 });
+            dlx.up.push(i);
+            dlx.down.push(i);
+            dlx.column.push(i);
+            dlx.row.push(usize::MAX);
+        }
+
+        let mut given_rows = Vec::new();
+        for row in 0..NUM_ROWS {
+            let first = dlx.column.len();
+            for (k, col) in row_columns(row).into_iter().enumerate() {
+                let node = first + k;
+                let header = col + 1;
+                dlx.left.push(if k == 0 { first + 3 } else { node - 1 });
+                dlx.right.push(if k == 3 { first } else { node + 1 });
+                dlx.up.push(dlx.up[header]);
+                dlx.down.push(header);
+                dlx.column.push(header);
+                dlx.row.push(row);
+
+                let last = dlx.up[header];
+                dlx.down[last] = node;
+                dlx.up[header] = node;
+                dlx.size[header] += 1;
+            }
+
+            let (r, c, d) = row_coords(row);
+            match board.at(r, c) {
+                Cell::Given(x) | Cell::NonGiven(x) if x as usize == d => {
+                    given_rows.push(first);
+                },
+                _ => {},
+            }
+        }
+
+        // filled cells are selected up front; a clash between them shows
+        // up as one of their columns being covered already
+        let mut covered = [false; NUM_COLUMNS + 1];
+        for first in given_rows {
+            let mut node = first;
+            loop {
+                let header = dlx.column[node];
+                if covered[header] {
+                    dlx.contradiction = true;
+                    return dlx;
+                }
+                covered[header] = true;
+                dlx.cover(header);
+                node = dlx.right[node];
+                if node == first { break; }
+            }
+        }
+
+        dlx
+    }
+
+    fn cover(&mut self, header: usize) {
+        let (l, r) = (self.left[header], self.right[header]);
+        self.right[l] = r;
+        self.left[r] = l;
+
+        let mut i = self.down[header];
+        while i != header {
+            let mut j = self.right[i];
+            while j != i {
+                let (u, d) = (self.up[j], self.down[j]);
+                self.down[u] = d;
+                self.up[d] = u;
+                self.size[self.column[j]] -= 1;
+                j = self.right[j];
+            }
+            i = self.down[i];
+        }
+    }
+
+    fn uncover(&mut self, header: usize) {
+        let mut i = self.up[header];
+        while i != header {
+            let mut j = self.left[i];
+            while j != i {
+                let (u, d) = (self.up[j], self.down[j]);
+                self.down[u] = j;
+                self.up[d] = j;
+                self.size[self.column[j]] += 1;
+                j = self.left[j];
+            }
+            i = self.up[i];
+        }
+
+        let (l, r) = (self.left[header], self.right[header]);
+        self.right[l] = header;
+        self.left[r] = header;
+    }
+
+    fn smallest_column(&self) -> usize {
+        let mut best = self.right[ROOT];
+        let mut header = self.right[best];
+        while header != ROOT {
+            if self.size[header] < self.size[best] { best = header; }
+            header = self.right[header];
+        }
+        best
+    }
+
+    fn current_solution(&self) -> Board {
+        let mut solution = self.board;
+        for &row in self.partial.iter() {
+            let (r, c, d) = row_coords(row);
+            solution.set(r, c, Cell::NonGiven(game::LEGAL_VALUES[d]));
+        }
+        solution
+    }
+
+    /// Algorithm X. Calls `on_solution` for every solution found and
+    /// stops as soon as it returns `false`. Returns `false` if stopped.
+    /// The links are restored on the way out, also when the budget runs out.
+    fn search<F: FnMut(Board) -> bool>(
+        &mut self,
+        budget: &Budget,
+        stats: &mut SolverStats,
+        on_solution: &mut F,
+    ) -> Result<bool, BudgetExhausted> {
+        stats.visit(self.partial.len());
+        budget.spend_node()?;
+        if self.right[ROOT] == ROOT {
+            return Ok(on_solution(self.current_solution()));
+        }
+
+        let header = self.smallest_column();
+        if self.size[header] == 0 {
+            stats.backtracks += 1;
+            return Ok(true);
+        }
+
+        self.cover(header);
+        let mut result = Ok(true);
+        let mut i = self.down[header];
+        while i != header {
+            stats.candidates_tried += 1;
+            self.partial.push(self.row[i]);
+            let mut j = self.right[i];
+            while j != i {
+                self.cover(self.column[j]);
+                j = self.right[j];
+            }
+
+            result = self.search(budget, stats, on_solution);
+
+            let mut j = self.left[i];
+            while j != i {
+                self.uncover(self.column[j]);
+                j = self.left[j];
+            }
+            self.partial.pop();
+
+            if result != Ok(true) { break; }
+            i = self.down[i];
+        }
+        self.uncover(header);
+
+        result
+    }
+
+    fn run<F: FnMut(Board) -> bool>(
+        &mut self,
+        budget: &Budget,
+        stats: &mut SolverStats,
+        mut on_solution: F,
+    ) -> Result<(), BudgetExhausted> {
+        if self.contradiction { return Ok(()); }
+        self.search(budget, stats, &mut on_solution)?;
+        Ok(())
+    }
+
+    pub fn first_solution(&mut self) -> Option<Board> {
+        self.collect_solutions(1).pop()
+    }
+
+    /// Counts solutions, stopping once `limit` of them have been found.
+    pub fn count_solutions(&mut self, limit: u64) -> u64 {
+        solver::unbounded(self.count_solutions_within(limit, &Budget::unlimited(), &mut SolverStats::default()))
+    }
+
+    fn count_solutions_within(
+        &mut self,
+        limit: u64,
+        budget: &Budget,
+        stats: &mut SolverStats,
+    ) -> Result<u64, BudgetExhausted> {
+        let mut count = 0;
+        if limit == 0 { return Ok(count); }
+        self.run(budget, stats, |_| {
+            count += 1;
+            count < limit
+        })?;
+        Ok(count)
+    }
+
+    /// Collects solutions, stopping once `limit` of them have been found.
+    pub fn collect_solutions(&mut self, limit: usize) -> Vec<Board> {
+        solver::unbounded(self.collect_solutions_within(limit, &Budget::unlimited(), &mut SolverStats::default()))
+    }
+
+    fn collect_solutions_within(
+        &mut self,
+        limit: usize,
+        budget: &Budget,
+        stats: &mut SolverStats,
+    ) -> Result<Vec<Board>, BudgetExhausted> {
+        let mut solutions = Vec::new();
+        if limit == 0 { return Ok(solutions); }
+        self.run(budget, stats, |board| {
+            solutions.push(board);
+            solutions.len() < limit
+        })?;
+        Ok(solutions)
+    }
+
+    pub fn all_solutions(&mut self) -> Vec<Board> {
+        self.collect_solutions(usize::MAX)
+    }
+}
+
+/// Solves `board` and returns the first solution found, with the
+/// previously empty cells filled as non-givens.
+pub fn solve(board: &Board) -> Option<Board> {
+    DancingLinks::new(board).first_solution()
+}
+
+pub fn count_solutions(board: &Board, limit: u64) -> u64 {
+    DancingLinks::new(board).count_solutions(limit)
+}
+
+pub fn all_solutions(board: &Board) -> Vec<Board> {
+    DancingLinks::new(board).all_solutions()
+}
+
+pub fn is_uniquely_solvable(board: &Board) -> bool {
+    count_solutions(board, 2) == 1
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DlxSolver;
+
+impl Solver for DlxSolver {
+    fn search_solutions(
+        &self,
+        board: &Board,
+        limit: usize,
+        budget: &Budget,
+        stats: &mut SolverStats,
+    ) -> Result<Vec<Board>, BudgetExhausted> {
+        DancingLinks::new(board).collect_solutions_within(limit, budget, stats)
+    }
+
+    fn search_count(
+        &self,
+        board: &Board,
+        limit: u64,
+        budget: &Budget,
+        stats: &mut SolverStats,
+    ) -> Result<u64, BudgetExhausted> {
+        DancingLinks::new(board).count_solutions_within(limit, budget, stats)
+    }
+}
+
+#[cfg(test)]
+mod dlx_solver_tests {
+    use super::*;
+    use crate::{ evaluator, utilities };
+
+    #[test]
+    fn test_solving_unique_puzzle() {
+        let board: Board = "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79"
+            .parse().unwrap();
+        let solution = solve(&board).unwrap();
+        assert!(evaluator::evaluate(&solution));
+        for pos in 0..81 {
+            let (r, c) = utilities::coords_from_pos(pos);
+            if let Cell::Given(x) = board.at(r, c) {
+                assert_eq!(solution.at(r, c), Cell::Given(x));
+            }
+        }
+        assert!(is_uniquely_solvable(&board));
+    }
+
+    #[test]
+    fn test_hard_17_clue_puzzle() {
+        let board: Board = "000000010400000000020000000000050407008000300001090000300400200050100000000806000"
+            .parse().unwrap();
+        assert_eq!(count_solutions(&board, u64::MAX), 1);
+        assert!(evaluator::evaluate(&solve(&board).unwrap()));
+    }
+
+    #[test]
+    fn test_counting_and_enumerating() {
+        let board: Board = "872415369691783542435926781763258194258194637149637258..........................."
+            .parse().unwrap();
+        assert_eq!(count_solutions(&board, 2), 2);
+        assert_eq!(count_solutions(&board, u64::MAX), 96);
+
+        let solutions = all_solutions(&board);
+        assert_eq!(solutions.len(), 96);
+        assert!(solutions.iter().all(evaluator::evaluate));
+        for (i, a) in solutions.iter().enumerate() {
+            for b in solutions.iter().skip(i + 1) {
+                assert!((0..81).any(|pos| a.at(pos / 9, pos % 9) != b.at(pos / 9, pos % 9)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_no_solution() {
+        let board: Board = "11..............................................................................."
+            .parse().unwrap();
+        assert!(solve(&board).is_none());
+        assert_eq!(count_solutions(&board, u64::MAX), 0);
+
+        let board: Board = "12345678.........9..............................................................."
+            .parse().unwrap();
+        assert!(all_solutions(&board).is_empty());
+    }
+
+    #[test]
+    fn test_links_restored_after_running_out() {
+        let board: Board = "872415369691783542435926781763258194258194637149637258..........................."
+            .parse().unwrap();
+        let mut dlx = DancingLinks::new(&board);
+        let budget = Budget::unlimited().with_max_nodes(20);
+        assert_eq!(
+            dlx.count_solutions_within(u64::MAX, &budget, &mut SolverStats::default()),
+            Err(BudgetExhausted)
+        );
+        assert_eq!(dlx.count_solutions(u64::MAX), 96);
+    }
+}
+
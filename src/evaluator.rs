@@ -1,68 +1,19 @@
-use std::collections::HashSet;
-
-use crate::{game::{ Board, Cell, Digit::{*} }, utilities};
+use crate::game::{ self, Board };
 
+/// Tells whether the board is completely and correctly filled in: every
+/// row, column and square holds all nine digits.
 pub fn evaluate(board: &Board) -> bool {
-    // evaluating every row
-    for i in 0..9 {
-        let mut set = HashSet::new();
-        for j in 0..9 {
-            match board.at(i, j) {
-                Cell::Given(x) => if !set.insert(x) {
-                    return false;
-                },
-                Cell::NonGiven(x) => if !set.insert(x) {
-                    return false;
-                },
-                Cell::Empty => { return false; },
-            }
-            if set.len() != j + 1 { return false; }
-        }
-    }
-
-    // evaluating every column
-    for j in 0..9 {
-        let mut set = HashSet::new();
-        for i in 0..9 {
-            match board.at(i, j) {
-                Cell::Given(x) => if !set.insert(x) {
-                    return false;
-                },
-                Cell::NonGiven(x) => if !set.insert(x) {
-                    return false;
-                },
-                Cell::Empty => { return false; },
-            }
-            if set.len() != i + 1 { return false; }
-        }
-    }
-
-    // evaluating every square
-    for s in 0..9 {
-        let (u, l) = utilities::square_limits_from_square(s);
-        let mut set = HashSet::new();
-        for i in u..=(u+2) {
-            for j in l..=(l+2) {
-                match board.at(i, j) {
-                    Cell::Given(x) => if !set.insert(x) {
-                        return false;
-                    },
-                    Cell::NonGiven(x) => if !set.insert(x) {
-                        return false;
-                    },
-                    Cell::Empty => { return false; },
-                }
-            }
-        }
-
-    }
-
-    true
+    (0..9).all(|i| {
+        board.row_mask(i) == game::ALL_DIGITS
+            && board.col_mask(i) == game::ALL_DIGITS
+            && board.square_mask(i) == game::ALL_DIGITS
+    })
 }
 
 #[cfg(test)]
 mod evaluator_tests {
     use super::*;
+    use crate::game::{ Cell, Digit::* };
     use crate::cli_display;
 
     #[test]
@@ -1,4 +1,6 @@
-use std::fmt;
+use std::{ error::Error, fmt, str::FromStr };
+
+use crate::{ evaluator, utilities };
 
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
@@ -6,6 +8,16 @@ pub enum Digit { D1, D2, D3, D4, D5, D6, D7, D8, D9 }
 
 pub const LEGAL_VALUES: [Digit; 9] = [ Digit::D1, Digit::D2, Digit::D3, Digit::D4, Digit::D5, Digit::D6, Digit::D7, Digit::D8, Digit::D9 ];
 
+/// Digit mask with all nine digits set.
+pub const ALL_DIGITS: u16 = 0b1_1111_1111;
+
+impl Digit {
+    /// The digit's bit in a digit mask, `D1` being the lowest.
+    pub fn bit(self) -> u16 {
+        1 << self as u16
+    }
+}
+
 impl fmt::Display for Digit {
    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
        match self {
@@ -30,7 +42,7 @@ pub enum Cell {
 }
 
 impl Cell {
-    pub fn to_string(&self, show_non_givens: bool) -> String {
+    pub fn to_string(self, show_non_givens: bool) -> String {
         match self {
             Cell::Given(x) => x.to_string(),
             Cell::NonGiven(x) => {
@@ -40,16 +52,39 @@ impl Cell {
             Cell::Empty => "_".to_string()
         }
     }
+
+    pub fn digit(self) -> Option<Digit> {
+        match self {
+            Cell::Given(x) | Cell::NonGiven(x) => Some(x),
+            Cell::Empty => None,
+        }
+    }
 }
 
+/// The grid together with a digit mask per row, column and square,
+/// kept up to date by `set`.
 #[derive(Copy, Debug, Clone)]
 pub struct Board {
     values: [[Cell; 9]; 9],
+    rows: [u16; 9],
+    cols: [u16; 9],
+    squares: [u16; 9],
 }
 
 impl Board {
     pub fn new(values: [[Cell; 9]; 9]) -> Self {
-        Self { values }
+        let mut board = Self {
+            values,
+            rows: [0; 9],
+            cols: [0; 9],
+            squares: [0; 9],
+        };
+        for r in 0..9 {
+            for c in 0..9 {
+                board.add_to_masks(r, c);
+            }
+        }
+        board
     }
 
     pub fn zeroed() -> Self {
@@ -67,7 +102,53 @@ impl Board {
         debug_assert!(row <= 8);
         debug_assert!(col <= 8);
 
+        let old = self.values[row][col];
         self.values[row][col] = digit;
+        // the old digit may still be elsewhere in a unit of an invalid
+        // board, so its units are rebuilt rather than just cleared
+        if old.digit().is_some() { self.rebuild_masks(row, col); }
+        self.add_to_masks(row, col);
+    }
+
+    fn add_to_masks(&mut self, row: usize, col: usize) {
+        let Some(x) = self.values[row][col].digit() else { return; };
+        self.rows[row] |= x.bit();
+        self.cols[col] |= x.bit();
+        self.squares[utilities::square_from_cell(row, col)] |= x.bit();
+    }
+
+    fn rebuild_masks(&mut self, row: usize, col: usize) {
+        let bits = |cells: &mut dyn Iterator<Item = Cell>| {
+            cells.filter_map(Cell::digit).fold(0, |mask, x| mask | x.bit())
+        };
+        let (u, l) = utilities::square_limits_from_cell(row, col);
+        self.rows[row] = bits(&mut self.values[row].iter().copied());
+        self.cols[col] = bits(&mut self.values.iter().map(|r| r[col]));
+        self.squares[utilities::square_from_cell(row, col)] = bits(
+            &mut (0..9).map(|k| self.values[u + k / 3][l + k % 3])
+        );
+    }
+
+    /// Digits placed in the row, as a mask of `Digit::bit`s.
+    pub fn row_mask(&self, row: usize) -> u16 {
+        self.rows[row]
+    }
+
+    pub fn col_mask(&self, col: usize) -> u16 {
+        self.cols[col]
+    }
+
+    /// Digits placed in square `square`, numbered row-major from the
+    /// top left like in `utilities::square_limits_from_square`.
+    pub fn square_mask(&self, square: usize) -> u16 {
+        self.squares[square]
+    }
+
+    /// Tells whether `digit` is missing from the row, column and square
+    /// of (row, col).
+    pub fn is_candidate(&self, row: usize, col: usize, digit: Digit) -> bool {
+        let used = self.rows[row] | self.cols[col] | self.squares[utilities::square_from_cell(row, col)];
+        used & digit.bit() == 0
     }
 
     pub fn row_cells(&self, row: usize) -> impl Iterator<Item = (usize, &Cell)> {
@@ -91,8 +172,429 @@ impl Board {
 
     pub fn set_non_given(&mut self, row: usize, col: usize, digit: Cell) {
         match self.at(row, col) {
-            Cell::Given(_) => return,
+            Cell::Given(_) => {},
             Cell::NonGiven(_) | Cell::Empty => self.set(row, col, digit),
         }
     }
 }
+
+/// A row, column or square, numbered from 0. Squares are numbered
+/// row-major from the top left.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Unit {
+    Row(usize),
+    Col(usize),
+    Box(usize),
+}
+
+impl Unit {
+    /// All 27 units: the rows, then the columns, then the squares.
+    pub fn all() -> impl Iterator<Item = Unit> {
+        (0..9).map(Unit::Row)
+            .chain((0..9).map(Unit::Col))
+            .chain((0..9).map(Unit::Box))
+    }
+
+    pub fn cells(self) -> [(usize, usize); 9] {
+        match self {
+            Unit::Row(r) => std::array::from_fn(|c| (r, c)),
+            Unit::Col(c) => std::array::from_fn(|r| (r, c)),
+            Unit::Box(s) => {
+                let (u, l) = utilities::square_limits_from_square(s);
+                std::array::from_fn(|k| (u + k / 3, l + k % 3))
+            },
+        }
+    }
+
+    pub fn contains(self, (r, c): (usize, usize)) -> bool {
+        match self {
+            Unit::Row(i) => r == i,
+            Unit::Col(j) => c == j,
+            Unit::Box(s) => utilities::square_from_cell(r, c) == s,
+        }
+    }
+}
+
+/// 1-based, like the cell names: `row 5`, `column 3`, `box 9`.
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Unit::Row(r) => write!(f, "row {}", r + 1),
+            Unit::Col(c) => write!(f, "column {}", c + 1),
+            Unit::Box(s) => write!(f, "box {}", s + 1),
+        }
+    }
+}
+
+/// A set of digits, stored as a mask of `Digit::bit`s.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Candidates(u16);
+
+impl Candidates {
+    pub const NONE: Candidates = Candidates(0);
+    pub const ALL: Candidates = Candidates(ALL_DIGITS);
+
+    pub fn from_mask(mask: u16) -> Self {
+        Candidates(mask & ALL_DIGITS)
+    }
+
+    pub fn mask(self) -> u16 {
+        self.0
+    }
+
+    pub fn contains(self, digit: Digit) -> bool {
+        self.0 & digit.bit() != 0
+    }
+
+    /// Returns `false` if the digit was already there.
+    pub fn insert(&mut self, digit: Digit) -> bool {
+        let added = !self.contains(digit);
+        self.0 |= digit.bit();
+        added
+    }
+
+    /// Returns `false` if the digit wasn't there.
+    pub fn remove(&mut self, digit: Digit) -> bool {
+        let removed = self.contains(digit);
+        self.0 &= !digit.bit();
+        removed
+    }
+
+    pub fn len(self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// The only digit of the set, if it has exactly one.
+    pub fn single(self) -> Option<Digit> {
+        if self.len() != 1 { return None; }
+        Some(LEGAL_VALUES[self.0.trailing_zeros() as usize])
+    }
+
+    /// Digits in increasing order.
+    pub fn iter(self) -> impl Iterator<Item = Digit> {
+        LEGAL_VALUES.into_iter().filter(move |&x| self.contains(x))
+    }
+}
+
+impl FromIterator<Digit> for Candidates {
+    fn from_iter<I: IntoIterator<Item = Digit>>(iter: I) -> Self {
+        let mut candidates = Candidates::NONE;
+        for x in iter {
+            candidates.insert(x);
+        }
+        candidates
+    }
+}
+
+/// The digits run together, e.g. `147`.
+impl fmt::Display for Candidates {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for x in self.iter() {
+            write!(f, "{}", x)?;
+        }
+        Ok(())
+    }
+}
+
+/// Pencil marks: a board together with the candidates of its empty
+/// cells. Filled cells have no candidates. Placing a digit takes it out
+/// of the candidates of the cell's peers; other than that, candidates
+/// only change when edited.
+#[derive(Copy, Clone, Debug)]
+pub struct CandidateGrid {
+    board: Board,
+    candidates: [[Candidates; 9]; 9],
+}
+
+impl CandidateGrid {
+    /// Candidates by basic elimination: every digit not yet placed in
+    /// the row, column or square of an empty cell.
+    pub fn from_board(board: &Board) -> Self {
+        let mut grid = CandidateGrid {
+            board: *board,
+            candidates: [[Candidates::NONE; 9]; 9],
+        };
+        for r in 0..9 {
+            for c in 0..9 {
+                grid.candidates[r][c] = grid.eliminated(r, c);
+            }
+        }
+        grid
+    }
+
+    // what basic elimination leaves in (r, c)
+    fn eliminated(&self, r: usize, c: usize) -> Candidates {
+        if self.board.at(r, c) != Cell::Empty { return Candidates::NONE; }
+        LEGAL_VALUES.into_iter()
+            .filter(|&x| self.board.is_candidate(r, c, x))
+            .collect()
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn candidates(&self, r: usize, c: usize) -> Candidates {
+        self.candidates[r][c]
+    }
+
+    /// Returns `false` if the digit wasn't a candidate.
+    pub fn remove_candidate(&mut self, r: usize, c: usize, digit: Digit) -> bool {
+        self.candidates[r][c].remove(digit)
+    }
+
+    /// Returns `false` if the digit was a candidate already or the cell
+    /// is filled.
+    pub fn add_candidate(&mut self, r: usize, c: usize, digit: Digit) -> bool {
+        if self.board.at(r, c) != Cell::Empty { return false; }
+        self.candidates[r][c].insert(digit)
+    }
+
+    /// Replaces the candidates of an empty cell; filled cells are left alone.
+    pub fn set_candidates(&mut self, r: usize, c: usize, candidates: Candidates) {
+        if self.board.at(r, c) != Cell::Empty { return; }
+        self.candidates[r][c] = candidates;
+    }
+
+    /// Fills the cell with `digit` as a non-given and removes the digit
+    /// from the candidates of its peers. Givens are left alone, and a
+    /// non-given is cleared first so its peers get the old digit back.
+    pub fn place(&mut self, r: usize, c: usize, digit: Digit) {
+        match self.board.at(r, c) {
+            Cell::Given(_) => return,
+            Cell::NonGiven(_) => self.clear(r, c),
+            Cell::Empty => {},
+        }
+        self.board.set(r, c, Cell::NonGiven(digit));
+        self.candidates[r][c] = Candidates::NONE;
+        for (i, j) in utilities::peers(r, c) {
+            self.candidates[i][j].remove(digit);
+        }
+    }
+
+    /// Empties a non-given cell. The cell gets its candidates back by
+    /// basic elimination, and so do the peers for the removed digit.
+    pub fn clear(&mut self, r: usize, c: usize) {
+        let Cell::NonGiven(digit) = self.board.at(r, c) else { return; };
+        self.board.set(r, c, Cell::Empty);
+        self.candidates[r][c] = self.eliminated(r, c);
+        for (i, j) in utilities::peers(r, c) {
+            if self.board.at(i, j) == Cell::Empty && self.board.is_candidate(i, j, digit) {
+                self.candidates[i][j].insert(digit);
+            }
+        }
+    }
+
+    /// Cells of `unit` that have `digit` as a candidate.
+    pub fn cells_with(&self, unit: Unit, digit: Digit) -> Vec<(usize, usize)> {
+        unit.cells().into_iter()
+            .filter(|&(r, c)| self.candidates[r][c].contains(digit))
+            .collect()
+    }
+
+    pub fn is_solved(&self) -> bool {
+        evaluator::evaluate(&self.board)
+    }
+
+    /// Empty cells left without any candidate.
+    pub fn dead_cells(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..81)
+            .map(utilities::coords_from_pos)
+            .filter(|&(r, c)| self.board.at(r, c) == Cell::Empty && self.candidates[r][c].is_empty())
+    }
+}
+
+/// Single line of 81 cells, `.` for empty ones, the inverse of `from_str`.
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for r in 0..9 {
+            for c in 0..9 {
+                match self.at(r, c).digit() {
+                    Some(x) => write!(f, "{}", x)?,
+                    None => write!(f, ".")?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseBoardError {
+    InvalidLength(usize),
+    InvalidCharacter(char),
+}
+
+impl fmt::Display for ParseBoardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseBoardError::InvalidLength(n) =>
+                write!(f, "expected 81 cells, found {}", n),
+            ParseBoardError::InvalidCharacter(ch) =>
+                write!(f, "invalid cell character '{}'", ch),
+        }
+    }
+}
+
+impl Error for ParseBoardError {}
+
+/// Parses the usual single-line format: 81 cells in row-major order,
+/// digits are givens and any of `.`, `0` or `_` marks an empty cell.
+impl FromStr for Board {
+    type Err = ParseBoardError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let len = s.chars().count();
+        if len != 81 { return Err(ParseBoardError::InvalidLength(len)); }
+
+        let mut board = Board::zeroed();
+        for (pos, ch) in s.chars().enumerate() {
+            let cell = match ch {
+                '1'..='9' => {
+                    let i = ch as usize - '1' as usize;
+                    Cell::Given(LEGAL_VALUES[i])
+                },
+                '.' | '0' | '_' => Cell::Empty,
+                _ => return Err(ParseBoardError::InvalidCharacter(ch)),
+            };
+            board.set(pos / 9, pos % 9, cell);
+        }
+        Ok(board)
+    }
+}
+
+#[cfg(test)]
+mod game_tests {
+    use super::*;
+    use Digit::*;
+
+    #[test]
+    fn test_masks_follow_set() {
+        let mut board = Board::zeroed();
+        board.set(0, 0, Cell::Given(D5));
+        board.set(4, 4, Cell::NonGiven(D5));
+        assert_eq!(board.row_mask(0), D5.bit());
+        assert_eq!(board.col_mask(4), D5.bit());
+        assert_eq!(board.square_mask(4), D5.bit());
+        assert!(!board.is_candidate(0, 8, D5));
+        assert!(!board.is_candidate(2, 2, D5));
+        assert!(board.is_candidate(2, 2, D1));
+
+        // the same digit twice in a row, removing one keeps the other
+        board.set(0, 7, Cell::Given(D5));
+        board.set(0, 0, Cell::Given(D3));
+        assert_eq!(board.row_mask(0), D3.bit() | D5.bit());
+        assert_eq!(board.square_mask(0), D3.bit());
+        board.set(0, 7, Cell::Empty);
+        assert_eq!(board.row_mask(0), D3.bit());
+        assert_eq!(board.col_mask(7), 0);
+    }
+
+    #[test]
+    fn test_masks_of_parsed_board() {
+        let board: Board = "872415369691783542435926781763258194258194637149637258387542916516879423924361875"
+            .parse().unwrap();
+        assert!((0..9).all(|i| board.row_mask(i) == ALL_DIGITS));
+        assert!((0..9).all(|i| board.col_mask(i) == ALL_DIGITS));
+        assert!((0..9).all(|i| board.square_mask(i) == ALL_DIGITS));
+        assert_eq!(board.non_givens_cleared().row_mask(0), ALL_DIGITS);
+
+        let board: Board = "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79"
+            .parse().unwrap();
+        assert_eq!(board.row_mask(0), D5.bit() | D3.bit() | D7.bit());
+        assert!(board.is_candidate(0, 2, D4));
+        assert!(!board.is_candidate(0, 2, D9));
+    }
+
+    #[test]
+    fn test_units() {
+        assert_eq!(Unit::all().count(), 27);
+        assert_eq!(Unit::Box(5).cells()[0], (3, 6));
+        assert_eq!(Unit::Col(2).cells()[8], (8, 2));
+        assert!(Unit::Box(8).contains((7, 6)));
+        assert!(!Unit::Row(1).contains((0, 1)));
+        assert_eq!(Unit::Col(2).to_string(), "column 3");
+        for unit in Unit::all() {
+            assert!(unit.cells().into_iter().all(|cell| unit.contains(cell)));
+        }
+    }
+
+    #[test]
+    fn test_candidates() {
+        let mut set: Candidates = [D1, D4, D7].into_iter().collect();
+        assert_eq!(set.to_string(), "147");
+        assert_eq!(set.len(), 3);
+        assert!(set.contains(D4));
+        assert!(!set.insert(D4));
+        assert!(set.remove(D4));
+        assert!(!set.remove(D4));
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![D1, D7]);
+        assert_eq!(set.single(), None);
+        set.remove(D1);
+        assert_eq!(set.single(), Some(D7));
+        assert_eq!(Candidates::from_mask(0xffff), Candidates::ALL);
+        assert!(Candidates::NONE.is_empty());
+    }
+
+    #[test]
+    fn test_candidate_grid() {
+        let board: Board = "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79"
+            .parse().unwrap();
+        let mut grid = CandidateGrid::from_board(&board);
+        assert_eq!(grid.candidates(0, 0), Candidates::NONE);
+        assert_eq!(grid.candidates(0, 2).to_string(), "124");
+        assert_eq!(grid.candidates(4, 4).to_string(), "5");
+        assert_eq!(grid.candidates(1, 2).to_string(), "247");
+        assert_eq!(grid.dead_cells().count(), 0);
+
+        // placing prunes the peers, clearing gives back what's possible
+        grid.place(0, 2, D4);
+        assert_eq!(grid.board().at(0, 2), Cell::NonGiven(D4));
+        assert_eq!(grid.candidates(0, 2), Candidates::NONE);
+        assert_eq!(grid.candidates(1, 2).to_string(), "27");
+        grid.clear(0, 2);
+        assert_eq!(grid.candidates(0, 2).to_string(), "124");
+        assert_eq!(grid.candidates(1, 2).to_string(), "247");
+
+        // editing
+        assert!(grid.remove_candidate(0, 2, D1));
+        assert!(!grid.remove_candidate(0, 2, D1));
+        assert_eq!(grid.candidates(0, 2).to_string(), "24");
+        assert!(grid.add_candidate(0, 2, D9));
+        assert!(!grid.add_candidate(0, 0, D9));
+        grid.set_candidates(0, 2, Candidates::NONE);
+        assert_eq!(grid.dead_cells().collect::<Vec<_>>(), vec![(0, 2)]);
+
+        assert_eq!(grid.cells_with(Unit::Row(0), D4), vec![(0, 5), (0, 6), (0, 7), (0, 8)]);
+        assert!(grid.cells_with(Unit::Box(4), D5).contains(&(4, 4)));
+        assert!(grid.cells_with(Unit::Box(0), D5).is_empty());
+
+        // givens can't be cleared
+        grid.clear(0, 0);
+        assert_eq!(grid.board().at(0, 0), Cell::Given(D5));
+    }
+
+    #[test]
+    fn test_placing_over_filled_cells() {
+        let board: Board = "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79"
+            .parse().unwrap();
+        let mut grid = CandidateGrid::from_board(&board);
+
+        // givens stay as they are
+        grid.place(0, 0, D1);
+        assert_eq!(grid.board().at(0, 0), Cell::Given(D5));
+        assert_eq!(grid.candidates(0, 2).to_string(), "124");
+
+        // overwriting a non-given gives its digit back to the peers
+        grid.place(0, 2, D4);
+        grid.place(0, 2, D2);
+        assert_eq!(grid.board().at(0, 2), Cell::NonGiven(D2));
+        assert_eq!(grid.candidates(1, 2).to_string(), "47");
+    }
+}
+
@@ -4,8 +4,7 @@ use crate::{
     game,
     game::{ Board, Cell, Digit },
     utilities,
-    naive_solver,
-    naive_solver::*,
+    solver::{ Budget, BudgetExhausted, SolverKind },
 };
 
 use rand::seq::SliceRandom;
@@ -23,33 +22,6 @@ fn generate_candidates_matrix<T: rand::Rng>(rng: &mut T) -> [[Vec::<Digit>; 9];
     )
 }
 
-fn is_valid_candidate(board: &Board, cand: Digit, r: usize, c: usize) -> bool {
-    for i in 0..r {
-        match board.at(i, c) {
-            Cell::Given(x) | Cell::NonGiven(x) => if x == cand { return false; },
-            Cell::Empty => {},
-        }
-    }
-    for j in 0..c {
-        match board.at(r, j) {
-            Cell::Given(x) | Cell::NonGiven(x) => if x == cand { return false; },
-            Cell::Empty => {},
-        }
-    }
-
-    let (u, l) = utilities::square_limits_from_cell(r, c);
-    for i in u..(u+3) {
-        for j in l..(l+3) {
-            match board.at(i, j) {
-                Cell::Given(x) | Cell::NonGiven(x) => if x == cand { return false; },
-                Cell::Empty => {},
-            }
-        }
-    }
-
-    true
-}
-
 pub fn generate_full_board<T: rand::Rng>(rng: &mut T) -> Board {
     let mut board = Board::zeroed();
     let mut candidates = generate_candidates_matrix(rng);
@@ -60,7 +32,7 @@ pub fn generate_full_board<T: rand::Rng>(rng: &mut T) -> Board {
 
         match candidates[r][c].pop() {
             Some(cand) => {
-                if !is_valid_candidate(&board, cand, r, c) { continue; }
+                if !board.is_candidate(r, c, cand) { continue; }
                 board.set(r, c, Cell::Given(cand));
                 pos += 1;
             },
@@ -78,24 +50,40 @@ pub fn generate_full_board<T: rand::Rng>(rng: &mut T) -> Board {
 }
 
 pub trait Generator : Debug {
-    fn generate_puzzle(&self) -> game::Board;
+    /// Generates a puzzle, however long it takes. Low numbers of givens
+    /// can take practically forever; see `generate_puzzle_within`.
+    fn generate_puzzle(&self) -> game::Board {
+        self.generate_puzzle_within(&Budget::unlimited())
+            .expect("an unlimited budget ran out")
+    }
+
+    /// Generates a puzzle, giving up once `budget` runs out. The budget
+    /// is shared by all the solver runs made along the way.
+    fn generate_puzzle_within(&self, budget: &Budget) -> Result<game::Board, BudgetExhausted>;
 }
 
 #[derive(Debug, Default)]
 pub struct NaiveGenerator {
-    pub num_givens: u8
+    pub num_givens: u8,
+    pub solver: SolverKind,
 }
 
 impl NaiveGenerator {
     pub fn new(set_givens: u8) -> NaiveGenerator {
         NaiveGenerator {
-            num_givens: if set_givens != 0 { set_givens } else { 39 }
+            num_givens: if set_givens != 0 { set_givens } else { 39 },
+            solver: SolverKind::default(),
         }
     }
+
+    pub fn with_solver(mut self, solver: SolverKind) -> Self {
+        self.solver = solver;
+        self
+    }
 }
 
 impl Generator for NaiveGenerator {
-    fn generate_puzzle(&self) -> game::Board {
+    fn generate_puzzle_within(&self, budget: &Budget) -> Result<game::Board, BudgetExhausted> {
         assert!(
             (17..81).contains(&self.num_givens),
             "Invalid argument given for number of givens: {}.\n\
@@ -103,36 +91,41 @@ impl Generator for NaiveGenerator {
             &self.num_givens
         );
 
+        let solver = self.solver.solver();
         let mut rng = rand::rng();
-        let mut board = generate_full_board(&mut rng);
-        'main_loop: loop {
-            board = board.clone();
+        let board = 'main_loop: loop {
+            budget.check()?;
+            let mut board = generate_full_board(&mut rng);
+
             // (pseudo)random order of cells to clear
             let mut positions: Vec<usize> = (0..81).collect();
             positions.shuffle(&mut rng);
 
-            // clear cells and test if board is a valid puzzle
-            // by trying to solve it
+            // clear cells as long as the puzzle keeps a unique solution;
+            // if we get stuck above the target, start over on a new board
             let mut current_givens = 81;
-            for i in 0..(81-17) as usize {
-                let (r, c) = utilities::coords_from_pos(positions[i]);
+            for &pos in positions.iter() {
+                let (r, c) = utilities::coords_from_pos(pos);
                 let val = board.at(r, c);
                 board.set(r, c, game::Cell::Empty);
 
-                match naive_solver::solve(&mut board) {
-                    SolverResult::Invalid => board.set(r, c, val),
-                    SolverResult::Valid => {
-                        current_givens -= 1;
-                        if current_givens == self.num_givens { break 'main_loop; }
+                if solver.is_uniquely_solvable_within(&board.non_givens_cleared(), budget)? {
+                    // the cleared cell keeps its digit as a non-given
+                    if let Cell::Given(x) = val {
+                        board.set(r, c, Cell::NonGiven(x));
                     }
+                    current_givens -= 1;
+                    if current_givens == self.num_givens { break 'main_loop board; }
+                } else {
+                    board.set(r, c, val);
                 }
             }
-        }
+        };
 
         let valid = evaluator::evaluate(&board);
-        debug_assert_eq!(valid, true, "The generated puzzle is not valid!");
+        debug_assert!(valid, "The generated puzzle is not valid!");
 
-        board
+        Ok(board)
     }
 }
 
@@ -150,7 +143,7 @@ mod naive_generator_tests {
 
             let valid = evaluator::evaluate(&board);
             if !valid { cli_display::print_board(&board); }
-            assert_eq!(valid, true);
+            assert!(valid);
         }
     }
 
@@ -158,12 +151,33 @@ mod naive_generator_tests {
     fn test_generating_puzzle() {
         for num_givens in 17..=80 {
             let generator = NaiveGenerator::new(num_givens);
+            let budget = Budget::unlimited().with_max_nodes(200_000);
 
-            let board = generator.generate_puzzle();
+            // sparse puzzles are rare and the budget may run out before
+            // one turns up, so only the puzzles generated are checked
+            let Ok(board) = generator.generate_puzzle_within(&budget) else { continue; };
 
             let valid = evaluator::evaluate(&board);
             if !valid { cli_display::print_board(&board); }
-            assert_eq!(valid, true);
+            assert!(valid);
+
+            let puzzle = board.non_givens_cleared();
+            let givens = (0..81)
+                .filter(|&pos| puzzle.at(pos / 9, pos % 9) != Cell::Empty)
+                .count();
+            assert_eq!(givens, num_givens as usize);
+            assert!(generator.solver.solver().is_uniquely_solvable(&puzzle));
         }
     }
+
+    #[test]
+    fn test_cancelled_generation() {
+        let generator = NaiveGenerator::new(17);
+        let budget = Budget::unlimited();
+        budget.cancel();
+        assert!(matches!(generator.generate_puzzle_within(&budget), Err(BudgetExhausted)));
+
+        let budget = Budget::unlimited().with_timeout(std::time::Duration::ZERO);
+        assert!(matches!(generator.generate_puzzle_within(&budget), Err(BudgetExhausted)));
+    }
 }
@@ -0,0 +1,13 @@
+pub mod game;
+pub mod generator;
+pub mod cli_display;
+pub mod naive_solver;
+pub mod smart_solver;
+pub mod dlx_solver;
+pub mod parallel_solver;
+pub mod solver;
+pub mod batch;
+pub mod cnf;
+pub mod evaluator;
+pub mod utilities;
+pub mod tui;
@@ -1,16 +1,21 @@
-mod game;
-mod generator;
-mod cli_display;
-mod naive_solver;
-mod evaluator;
-mod utilities;
-mod tui;
-
-use crate::{
+use ku::{
+    batch,
+    batch::{ BatchMode, BatchOptions },
+    cli_display,
+    cnf,
+    game::Board,
     generator::*,
+    smart_solver,
+    smart_solver::LogicalSolver,
+    solver::{ Budget, SolverKind, SolverResult },
+    tui,
+    utilities,
 };
 
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
+use color_eyre::eyre::WrapErr;
+
+use std::{ fs, io, path::PathBuf, sync::{ Arc, atomic::AtomicBool }, time::Duration };
 
 #[derive(Parser)]
 #[command(name = "ku")]
@@ -23,20 +28,193 @@ struct Cli {
     command: Option<Commands>
 }
 
+/// Limits for searches that could otherwise run for a very long time
+#[derive(Args)]
+struct BudgetArgs {
+    /// Give up after this many seconds
+    #[arg(long)]
+    timeout: Option<f64>,
+    /// Give up after visiting this many search nodes
+    #[arg(long)]
+    max_nodes: Option<u64>,
+}
+
+impl BudgetArgs {
+    fn budget(&self) -> Budget {
+        let mut budget = Budget::unlimited();
+        if let Some(secs) = self.timeout {
+            budget = budget.with_timeout(Duration::from_secs_f64(secs));
+        }
+        if let Some(max_nodes) = self.max_nodes {
+            budget = budget.with_max_nodes(max_nodes);
+        }
+        budget
+    }
+}
+
 #[derive(Subcommand)]
 enum Commands {
+    /// Generate a puzzle with a unique solution
     Generate {
         #[arg(short, long)]
         num_givens: u8,
+        #[arg(short, long, value_enum, default_value_t)]
+        solver: SolverKind,
+        #[command(flatten)]
+        budget: BudgetArgs,
+    },
+    /// Solve a puzzle and tell whether its solution is unique
+    Solve {
+        /// 81 cells in row-major order, `.`, `0` or `_` for empty ones
+        #[arg(required_unless_present = "batch")]
+        puzzle: Option<String>,
+        #[arg(short, long, value_enum, default_value_t)]
+        solver: SolverKind,
+        /// Report search statistics
+        #[arg(long)]
+        stats: bool,
+        #[command(flatten)]
+        budget: BudgetArgs,
+        /// Read puzzles from stdin, one per line, and print one result
+        /// line for each; always uses the smart solver
+        #[arg(long, conflicts_with = "puzzle")]
+        batch: Option<BatchMode>,
+        /// Threads for the batch mode
+        #[arg(long, requires = "batch")]
+        threads: Option<usize>,
+    },
+    /// Solve a puzzle step by step with logical techniques
+    Explain {
+        puzzle: String,
+        /// Also use the techniques that rely on the puzzle having a unique
+        /// solution, once checked that it has
+        #[arg(short, long)]
+        unique: bool,
+        #[command(flatten)]
+        budget: BudgetArgs,
+    },
+    /// Print every solution of a puzzle, one per line
+    Enumerate {
+        puzzle: String,
+        #[arg(short, long)]
+        limit: Option<usize>,
+    },
+    /// Print a puzzle as a DIMACS CNF formula, or read back a SAT
+    /// solver's model of that formula
+    Cnf {
+        puzzle: String,
+        /// File with the SAT solver's output, to print as a solved board
+        #[arg(short, long)]
+        model: Option<PathBuf>,
+    },
+    Tui {
+        #[arg(short, long, value_enum, default_value_t)]
+        solver: SolverKind,
     },
-    Tui,
     Gui,
 }
 
-fn generate(num: &u8) {
-    let generator = NaiveGenerator::new(*num);
-    let board = generator.generate_puzzle();
+fn generate(num: &u8, solver: &SolverKind, budget: &BudgetArgs) -> color_eyre::Result<()> {
+    let generator = NaiveGenerator::new(*num).with_solver(*solver);
+    let board = generator.generate_puzzle_within(&budget.budget())
+        .wrap_err_with(|| format!("Could not generate a puzzle with {} givens", num))?;
     cli_display::print_board(&board);
+
+    Ok(())
+}
+
+fn solve(puzzle: &str, solver: &SolverKind, stats: &bool, budget: &BudgetArgs) -> color_eyre::Result<()> {
+    let board: Board = puzzle.parse()?;
+
+    let (result, solver_stats) = solver.solver().analyse_with_stats(&board, &budget.budget());
+    match result {
+        Err(_) => println!("Gave up, the search budget ran out."),
+        Ok(SolverResult::NoSolution) => println!("The puzzle has no solution."),
+        Ok(SolverResult::Unique(solution)) => cli_display::print_board(&solution),
+        Ok(result @ SolverResult::Multiple { first, second }) => {
+            let cells: Vec<String> = result.differing_cells().iter()
+                .map(|&(r, c)| utilities::cell_name(r, c))
+                .collect();
+            println!("The puzzle has multiple solutions, differing in {}:", cells.join(" "));
+            cli_display::print_board(&first);
+            cli_display::print_board(&second);
+        },
+    }
+    if *stats { println!("{}", solver_stats); }
+
+    Ok(())
+}
+
+fn solve_batch(mode: &BatchMode, threads: &Option<usize>, stats: &bool, budget: &BudgetArgs) -> color_eyre::Result<()> {
+    let mut options = BatchOptions { mode: *mode, ..BatchOptions::default() };
+    if let Some(threads) = threads { options.threads = *threads; }
+
+    // the limits are per puzzle, only cancelling covers the whole batch
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let new_budget = || budget.budget().with_cancel_flag(cancelled.clone());
+    let summary = batch::run(io::stdin().lock(), io::stdout().lock(), &options, new_budget)?;
+    if *stats {
+        eprintln!(
+            "puzzles: {}, unique: {}, multiple: {}, no solution: {}, invalid: {}, gave up: {}",
+            summary.puzzles, summary.unique, summary.multiple,
+            summary.no_solution, summary.invalid, summary.gave_up
+        );
+    }
+
+    Ok(())
+}
+
+fn explain(puzzle: &str, unique: &bool, budget: &BudgetArgs) -> color_eyre::Result<()> {
+    let board: Board = puzzle.parse()?;
+
+    let budget = budget.budget();
+    let mut solver = LogicalSolver::default();
+    if *unique {
+        match SolverKind::default().solver().is_uniquely_solvable_within(&board, &budget) {
+            Ok(true) => solver = solver.assuming_uniqueness(),
+            Ok(false) => println!("The puzzle doesn't have a unique solution, so none is assumed."),
+            Err(_) => println!("Gave up checking that the solution is unique, so it isn't assumed."),
+        }
+    }
+    let solution = solver.solve_within(&board, &budget);
+    for (i, step) in solution.steps.iter().enumerate() {
+        println!("{:>3}. {}", i + 1, step);
+    }
+    cli_display::print_board(solution.grid.board());
+    if solution.is_solved() {
+        println!("Solved without guessing.");
+    } else if solution.is_exhausted() {
+        println!("Gave up after {} steps, the search budget ran out.", solution.steps.len());
+    } else {
+        println!("Stuck after {} steps, no known technique applies.", solution.steps.len());
+    }
+
+    Ok(())
+}
+
+fn enumerate(puzzle: &str, limit: &Option<usize>) -> color_eyre::Result<()> {
+    let board: Board = puzzle.parse()?;
+
+    for solution in smart_solver::solutions(&board, *limit) {
+        println!("{}", solution);
+    }
+
+    Ok(())
+}
+
+fn cnf(puzzle: &str, model: &Option<PathBuf>) -> color_eyre::Result<()> {
+    let board: Board = puzzle.parse()?;
+
+    match model {
+        None => cnf::write_dimacs(&board, &mut io::stdout().lock())?,
+        Some(path) => {
+            let model = fs::read_to_string(path)
+                .wrap_err_with(|| format!("Could not read {}", path.display()))?;
+            cli_display::print_board(&cnf::from_model(&board, &model)?);
+        },
+    }
+
+    Ok(())
 }
 
 fn main() -> color_eyre::Result<()> {
@@ -45,8 +223,16 @@ fn main() -> color_eyre::Result<()> {
     let cli = Cli::parse();
 
     match &cli.command {
-        Some(Commands::Generate { num_givens: num }) => generate(num),
-        Some(Commands::Tui) => { return tui::run(); },
+        Some(Commands::Generate { num_givens: num, solver, budget }) => generate(num, solver, budget)?,
+        Some(Commands::Solve { batch: Some(mode), threads, stats, budget, .. }) =>
+            solve_batch(mode, threads, stats, budget)?,
+        Some(Commands::Solve { puzzle: Some(puzzle), solver, stats, budget, .. }) =>
+            solve(puzzle, solver, stats, budget)?,
+        Some(Commands::Solve { .. }) => unreachable!("clap requires a puzzle or --batch"),
+        Some(Commands::Explain { puzzle, unique, budget }) => explain(puzzle, unique, budget)?,
+        Some(Commands::Enumerate { puzzle, limit }) => enumerate(puzzle, limit)?,
+        Some(Commands::Cnf { puzzle, model }) => cnf(puzzle, model)?,
+        Some(Commands::Tui { solver }) => { return tui::run(*solver); },
         Some(Commands::Gui) => { panic!("Not implemented!"); },
         None => {}
     }
@@ -1,226 +1,244 @@
 use crate::{
     game,
     game::{ Board, Cell, Digit },
+    solver::{ Budget, BudgetExhausted, Solver, SolverResult, SolverStats },
     utilities,
 };
 
-use std::ops::Index;
-use std::ops::IndexMut;
-
-struct CandidatesBoard<'a> {
-    board: &'a mut Board,
-    candidates: [[Option<Vec<Digit>>;9];9],
-    first_pos: usize,
-    last_pos: usize,
-    current_pos: usize
+/// The board being filled in together with the digits still allowed in
+/// each empty cell. Placing a digit removes it from the candidates of
+/// its peers right away, and remembers which ones so it can be undone.
+struct CandidatesBoard {
+    board: Board,
+    candidates: [[Option<Vec<Digit>>; 9]; 9],
+    // peers that lost a candidate to each placement still on the board
+    eliminations: Vec<Vec<(usize, usize)>>,
 }
 
-impl Index<usize> for CandidatesBoard<'_> {
-    type Output = Option<Vec<Digit>>;
-    fn index(&self, pos: usize) -> &Self::Output {
-        if pos >= 81 { return &None; }
-        &self.candidates[pos / 9][pos % 9]
-    }
-}
-impl IndexMut<usize> for CandidatesBoard<'_> {
-    fn index_mut(&mut self, pos: usize) -> &mut Option<Vec<Digit>> {
-        &mut self.candidates[pos / 9][pos % 9]
-    }
-}
-impl Index<(usize,usize)> for CandidatesBoard<'_> {
-    type Output = Option<Vec<Digit>>;
-    fn index(&self, xy: (usize,usize)) -> &Self::Output {
-        &self.candidates[xy.0][xy.1]
-    }
-}
-impl IndexMut<(usize,usize)> for CandidatesBoard<'_> {
-    fn index_mut(&mut self, xy: (usize,usize)) -> &mut Option<Vec<Digit>> {
-        &mut self.candidates[xy.0][xy.1]
-    }
-}
-impl<'a> CandidatesBoard<'a> {
-    fn new(board: &'a mut Board) -> Self {
-        let mut candidates: [[Option<Vec<Digit>>; 9]; 9]  = Default::default();
-        let mut first_non_given = None; let mut last_non_given = 0;
-        for i in 0..9 {
-            for j in 0..9 {
-                if first_non_given.is_none() && let Cell::Empty = board.at(i, j) {
-                    first_non_given = Some(i * 9 + j);
-                }
-                if let Cell::Empty = board.at(i, j) {
-                    last_non_given = i * 9 + j;
-                    candidates[i][j] = Some(game::LEGAL_VALUES.into_iter().collect());
-                }
+impl CandidatesBoard {
+    fn new(board: &Board) -> Self {
+        let mut candidates: [[Option<Vec<Digit>>; 9]; 9] = Default::default();
+        for (r, row) in candidates.iter_mut().enumerate() {
+            for (c, cands) in row.iter_mut().enumerate() {
+                if board.at(r, c) != Cell::Empty { continue; }
+                *cands = Some(
+                    game::LEGAL_VALUES.into_iter()
+                        .filter(|&x| board.is_candidate(r, c, x))
+                        .collect()
+                );
             }
         }
         CandidatesBoard {
-            board: board,
-            candidates: candidates,
-            first_pos: first_non_given.unwrap(),
-            last_pos: last_non_given,
-            current_pos: first_non_given.unwrap()
+            board: *board,
+            candidates,
+            eliminations: Vec::new(),
         }
     }
 
-    fn is_first_nongiven(&self) -> bool { self.current_pos == self.first_pos }
-    fn is_last_nongiven(&self) -> bool { self.current_pos == self.last_pos }
-
-    fn current_cell_candidates(&mut self) -> &mut Option<Vec<Digit>> {
-        let pos = self.current_pos;
-        &mut self[pos]
-    }
-
-    fn pop_current_candidate(&mut self) {
-        if let Some(cands) = self.current_cell_candidates() {
-            cands.pop();
+    /// Empty cell with the fewest candidates left, or `None` if the
+    /// board is full.
+    fn most_constrained(&self) -> Option<(usize, usize)> {
+        let mut best = None;
+        let mut best_count = usize::MAX;
+        for r in 0..9 {
+            for c in 0..9 {
+                let Some(cands) = &self.candidates[r][c] else { continue; };
+                if cands.len() < best_count {
+                    best = Some((r, c));
+                    best_count = cands.len();
+                    if best_count <= 1 { return best; }
+                }
+            }
         }
+        best
     }
 
-    fn current_candidate(&mut self) -> Option<Digit> {
-        if let Some(cands) = self.current_cell_candidates().as_ref() {
-            cands.last().cloned()
-        } else {
-            None
+    /// Places `digit`, whose candidates must have been taken out of the
+    /// cell already, and prunes it from the peers. Returns `false` if some
+    /// empty peer is left without candidates; `remove` undoes it either way.
+    fn place(&mut self, r: usize, c: usize, digit: Digit) -> bool {
+        self.board.set(r, c, Cell::NonGiven(digit));
+        let mut pruned = Vec::new();
+        let mut dead_end = false;
+        for (i, j) in utilities::peers(r, c) {
+            let Some(cands) = self.candidates[i][j].as_mut() else { continue; };
+            let Some(k) = cands.iter().position(|&x| x == digit) else { continue; };
+            cands.swap_remove(k);
+            pruned.push((i, j));
+            dead_end |= cands.is_empty();
         }
+        self.eliminations.push(pruned);
+        !dead_end
     }
 
-    fn reset_current_candidates(&mut self) {
-        *self.current_cell_candidates() = Some(game::LEGAL_VALUES.into_iter().collect());
-    }
-
-    fn next_non_given(&mut self) {
-        for i in (self.current_pos + 1)..81 {
-            let cands = &self[i];
-            if !cands.is_none() { self.current_pos = i; return; }
-        }
-    }
-    fn prev_non_given(&mut self) {
-        for i in (0..self.current_pos).rev() {
-            let cands = &self[i];
-            if !cands.is_none() { self.current_pos = i; return; }
+    /// Takes back the last placement, made at (r, c).
+    fn remove(&mut self, r: usize, c: usize) {
+        let Some(digit) = self.board.at(r, c).digit() else { return; };
+        for (i, j) in self.eliminations.pop().unwrap_or_default() {
+            if let Some(cands) = self.candidates[i][j].as_mut() {
+                cands.push(digit);
+            }
         }
+        self.board.set(r, c, Cell::Empty);
     }
+}
 
-    fn is_current_candidate_valid(&self) -> bool {
-        let r = self.current_pos / 9;
-        let c = self.current_pos % 9;
-        let cand = self[self.current_pos].as_ref().unwrap().last().unwrap();
-
-        for i in 0..r {
-            match self.board.at(i, c) {
-                Cell::Given(x) | Cell::NonGiven(x) => {
-                    if x == *cand { return false; }
-                },
-                Cell::Empty => {},
-            }
-        }
-        for j in 0..c {
-            match self.board.at(r, j) {
-                Cell::Given(x) | Cell::NonGiven(x) => {
-                    if x == *cand { return false; }
-                },
-                Cell::Empty => {},
+/// Returns `true` if two filled cells see each other with the same digit.
+fn filled_cells_clash(board: &Board) -> bool {
+    for r in 0..9 {
+        for c in 0..9 {
+            let Some(x) = board.at(r, c).digit() else { continue; };
+            if utilities::peers(r, c).any(|(i, j)| board.at(i, j).digit() == Some(x)) {
+                return true;
             }
         }
-        let (u, l) = utilities::square_limits_from_cell(r, c);
-        for i in u..(u+3) {
-            for j in l..(l+3) {
-                match self.board.at(i, j) {
-                    Cell::Given(x) | Cell::NonGiven(x) => {
-                        if x == *cand { return false; }
-                    },
-                    Cell::Empty => {},
-                }
-            }
-        }
-
-        true
     }
+    false
+}
 
-    pub fn curr_cell_no_candidates(&mut self) -> bool {
-        self.current_cell_candidates().as_ref().unwrap().is_empty()
+/// Backtracking that always fills in the empty cell with the fewest
+/// candidates next. Returns `false` if `on_solution` asked to stop.
+fn search_from<F: FnMut(Board) -> bool>(
+    cands: &mut CandidatesBoard,
+    depth: usize,
+    budget: &Budget,
+    stats: &mut SolverStats,
+    on_solution: &mut F,
+) -> Result<bool, BudgetExhausted> {
+    stats.visit(depth);
+    budget.spend_node()?;
+    let Some((r, c)) = cands.most_constrained() else {
+        return Ok(on_solution(cands.board));
+    };
+
+    let options = cands.candidates[r][c].take().unwrap_or_default();
+    if options.is_empty() { stats.backtracks += 1; }
+
+    let mut keep_going = Ok(true);
+    for &digit in options.iter() {
+        stats.candidates_tried += 1;
+        keep_going = if cands.place(r, c, digit) {
+            search_from(cands, depth + 1, budget, stats, on_solution)
+        } else {
+            stats.backtracks += 1;
+            Ok(true)
+        };
+        cands.remove(r, c);
+        if keep_going != Ok(true) { break; }
     }
+    cands.candidates[r][c] = Some(options);
 
-    pub fn generate_solution(&mut self) {
-        for i in 0..9 {
-            for j in 0..9 {
-                if let Cell::Empty = self.board.at(i, j)
-                {
-                    let value = self.candidates[i][j]
-                        .as_ref().unwrap()
-                        .last().unwrap().clone();
-                    self.board.set(i, j, Cell::NonGiven(value));
-                }
-            }
-        }
+    keep_going
+}
+
+/// Calls `on_solution` for every solution found until it returns `false`.
+fn search<F: FnMut(Board) -> bool>(
+    board: &Board,
+    budget: &Budget,
+    stats: &mut SolverStats,
+    mut on_solution: F,
+) -> Result<(), BudgetExhausted> {
+    if filled_cells_clash(board) {
+        stats.visit(0);
+        return budget.spend_node();
     }
 
+    let mut cands = CandidatesBoard::new(board);
+    search_from(&mut cands, 0, budget, stats, &mut on_solution)?;
+    Ok(())
 }
 
-pub enum SolverResult {
-    Invalid,
-    Valid,
+/// Tells apart puzzles with no, exactly one, or several solutions.
+pub fn solve(board: &Board) -> SolverResult {
+    NaiveSolver.analyse(board)
 }
 
-pub fn solve(board: &mut Board) -> SolverResult {
-    let mut solutions_count: u64 = 0;
-    let mut board_candidates = CandidatesBoard::new(board);
-    // let mut solution = board.clone();
-    'outer: loop {
-        // current candidate is valid
-        if board_candidates.is_current_candidate_valid() {
-            if board_candidates.is_last_nongiven() {
-                solutions_count += 1;
-                // invalid puzzle
-                if solutions_count >= 2 { return SolverResult::Invalid; }
-
-                // set solution board
-                board_candidates.generate_solution();
-
-                // only one non-given in puzzle (trivial case), valid solution
-                if board_candidates.is_first_nongiven() {
-                    return SolverResult::Valid;
-                }
+/// Like `solve`, but gives up once `budget` runs out.
+pub fn solve_within(board: &Board, budget: &Budget) -> Result<SolverResult, BudgetExhausted> {
+    NaiveSolver.analyse_within(board, budget)
+}
 
-                // recursively reset cell candidates until
-                // first available cell candidate
-                loop {
-                    board_candidates.reset_current_candidates();
-                    board_candidates.prev_non_given();
-                    board_candidates.pop_current_candidate();
-
-                    if board_candidates.current_candidate().is_none() {
-                        if board_candidates.is_first_nongiven() {
-                            return SolverResult::Valid;
-                        }
-                    } else {
-                        continue 'outer;
-                    }
-                }
-            } else {
-                board_candidates.next_non_given();
-            }
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NaiveSolver;
+
+impl Solver for NaiveSolver {
+    fn search_solutions(
+        &self,
+        board: &Board,
+        limit: usize,
+        budget: &Budget,
+        stats: &mut SolverStats,
+    ) -> Result<Vec<Board>, BudgetExhausted> {
+        let mut solutions = Vec::new();
+        if limit == 0 { return Ok(solutions); }
+        search(board, budget, stats, |solution| {
+            solutions.push(solution);
+            solutions.len() < limit
+        })?;
+        Ok(solutions)
+    }
+
+    fn search_count(
+        &self,
+        board: &Board,
+        limit: u64,
+        budget: &Budget,
+        stats: &mut SolverStats,
+    ) -> Result<u64, BudgetExhausted> {
+        let mut count = 0;
+        if limit == 0 { return Ok(count); }
+        search(board, budget, stats, |_| {
+            count += 1;
+            count < limit
+        })?;
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod naive_solver_tests {
+    use super::*;
+    use crate::evaluator;
+
+    #[test]
+    fn test_hard_puzzles() {
+        let puzzles = [
+            "000000010400000000020000000000050407008000300001090000300400200050100000000806000",
+            "1....7.9..3..2...8..96..5....53..9...1..8...26....4...3......1..4......7..7...3..",
+        ];
+        for puzzle in puzzles {
+            let board: Board = puzzle.parse().unwrap();
+            let SolverResult::Unique(solution) = solve(&board) else { panic!("{}", puzzle); };
+            assert!(evaluator::evaluate(&solution));
         }
-        // current candidate is invalid
-        else {
-            loop {
-                board_candidates.pop_current_candidate();
-                if board_candidates.curr_cell_no_candidates() {
-                    if board_candidates.is_first_nongiven() {
-                        return match solutions_count {
-                            2..   => SolverResult::Invalid,
-                            0..=1 => SolverResult::Valid,
-                        }
-                    }
-                    // since no valid candidates in current cell,
-                    // pop previous cell's candidate
-                    board_candidates.reset_current_candidates();
-                    board_candidates.prev_non_given();
-                    board_candidates.pop_current_candidate();
-                } else {
-                    continue 'outer;
-                }
+    }
+
+    #[test]
+    fn test_candidates_restored_after_search() {
+        let board: Board = "872415369691783542435926781763258194258194637149637258..........................."
+            .parse().unwrap();
+        let mut cands = CandidatesBoard::new(&board);
+        let before = cands.candidates.clone();
+
+        let mut count = 0;
+        let result = search_from(&mut cands, 0, &Budget::unlimited(), &mut SolverStats::default(), &mut |_| {
+            count += 1;
+            true
+        });
+        assert_eq!(result, Ok(true));
+        assert_eq!(count, 96);
+
+        // same candidates, though not necessarily in the same order
+        for (r, row) in cands.candidates.iter_mut().enumerate() {
+            for (c, cell) in row.iter_mut().enumerate() {
+                if let Some(cell) = cell.as_mut() { cell.sort_by_key(|&x| x as u8); }
+                let mut expected = before[r][c].clone();
+                if let Some(expected) = expected.as_mut() { expected.sort_by_key(|&x| x as u8); }
+                assert_eq!(*cell, expected);
             }
         }
+        assert!((0..81).all(|pos| cands.board.at(pos / 9, pos % 9) == board.at(pos / 9, pos % 9)));
+        assert!(cands.eliminations.is_empty());
     }
 }
+
@@ -0,0 +1,298 @@
+use std::{
+    iter,
+    sync::{
+        Mutex,
+        atomic::{ AtomicBool, AtomicU64, AtomicUsize, Ordering },
+    },
+    thread,
+};
+
+use crossbeam::deque::{ Injector, Steal, Stealer, Worker };
+
+use crate::{
+    game,
+    game::Board,
+    smart_solver::SearchState,
+    solver::{ Budget, BudgetExhausted, Solver, SolverStats },
+};
+
+// a subtree of the search, rooted at `state`
+struct Task {
+    state: SearchState,
+    depth: usize,
+}
+
+/// Splits the search tree into tasks down to `split_depth` branching
+/// levels and lets `threads` workers share them by work stealing. Below
+/// that depth every task is searched sequentially.
+#[derive(Debug, Clone, Copy)]
+pub struct ParallelSolver {
+    pub threads: usize,
+    pub split_depth: usize,
+}
+
+impl Default for ParallelSolver {
+    fn default() -> Self {
+        ParallelSolver {
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            split_depth: 4,
+        }
+    }
+}
+
+// shared by all workers of one run
+struct Pool<'a, F> {
+    injector: Injector<Task>,
+    stealers: Vec<Stealer<Task>>,
+    // tasks pushed but not yet finished; zero means the search is over
+    pending: AtomicUsize,
+    stop: AtomicBool,
+    // set if the search stopped because the budget ran out
+    exhausted: AtomicBool,
+    budget: &'a Budget,
+    on_solution: &'a F,
+}
+
+impl<F: Fn(&SearchState) -> bool + Sync> Pool<'_, F> {
+    fn find_task(&self, local: &Worker<Task>) -> Option<Task> {
+        local.pop().or_else(|| {
+            iter::repeat_with(|| {
+                self.injector.steal_batch_and_pop(local)
+                    .or_else(|| self.stealers.iter().map(|s| s.steal()).collect())
+            })
+            .find(|s| !s.is_retry())
+            .and_then(Steal::success)
+        })
+    }
+
+    fn solution_found(&self, state: &SearchState) -> bool {
+        if self.stop.load(Ordering::Relaxed) { return false; }
+        if !(self.on_solution)(state) {
+            self.stop.store(true, Ordering::Relaxed);
+            return false;
+        }
+        true
+    }
+
+    fn give_up(&self) {
+        // a search already stopped by its caller has found all it needed
+        if !self.stop.swap(true, Ordering::Relaxed) {
+            self.exhausted.store(true, Ordering::Relaxed);
+        }
+    }
+
+    // Searches below the split depth like `smart_solver::search`, but
+    // gives up on the subtree as soon as the pool is stopped, instead of
+    // only at the next solution.
+    fn search(
+        &self,
+        mut state: SearchState,
+        depth: usize,
+        stats: &mut SolverStats,
+    ) -> Result<bool, BudgetExhausted> {
+        if self.stop.load(Ordering::Relaxed) { return Ok(false); }
+        stats.visit(depth);
+        self.budget.spend_node()?;
+        if !state.propagate() {
+            stats.backtracks += 1;
+            return Ok(true);
+        }
+
+        let Some(pos) = state.most_constrained() else {
+            return Ok(self.solution_found(&state));
+        };
+
+        let mut cands = state.candidates[pos];
+        while cands != 0 {
+            let digit = game::LEGAL_VALUES[cands.trailing_zeros() as usize];
+            cands &= cands - 1;
+
+            stats.candidates_tried += 1;
+            let mut next = state;
+            if !next.assign(pos, digit) {
+                stats.backtracks += 1;
+                continue;
+            }
+            if !self.search(next, depth + 1, stats)? { return Ok(false); }
+        }
+        Ok(true)
+    }
+
+    fn run_task(&self, task: Task, local: &Worker<Task>, split_depth: usize, stats: &mut SolverStats) {
+        if task.depth >= split_depth {
+            if self.search(task.state, task.depth, stats).is_err() { self.give_up(); }
+            return;
+        }
+
+        stats.visit(task.depth);
+        if self.budget.spend_node().is_err() {
+            self.give_up();
+            return;
+        }
+        let mut state = task.state;
+        if !state.propagate() {
+            stats.backtracks += 1;
+            return;
+        }
+
+        let Some(pos) = state.most_constrained() else {
+            self.solution_found(&state);
+            return;
+        };
+
+        let mut cands = state.candidates[pos];
+        while cands != 0 {
+            let digit = game::LEGAL_VALUES[cands.trailing_zeros() as usize];
+            cands &= cands - 1;
+
+            stats.candidates_tried += 1;
+            let mut next = state;
+            if next.assign(pos, digit) {
+                self.pending.fetch_add(1, Ordering::SeqCst);
+                local.push(Task { state: next, depth: task.depth + 1 });
+            } else {
+                stats.backtracks += 1;
+            }
+        }
+    }
+
+    fn work(&self, local: Worker<Task>, split_depth: usize) -> SolverStats {
+        let mut stats = SolverStats::default();
+        loop {
+            if self.stop.load(Ordering::Relaxed) { return stats; }
+            match self.find_task(&local) {
+                Some(task) => {
+                    self.run_task(task, &local, split_depth, &mut stats);
+                    self.pending.fetch_sub(1, Ordering::SeqCst);
+                },
+                None => {
+                    if self.pending.load(Ordering::SeqCst) == 0 { return stats; }
+                    thread::yield_now();
+                },
+            }
+        }
+    }
+}
+
+impl ParallelSolver {
+    /// Calls `on_solution` from the worker threads for every solution
+    /// found, until one of the calls returns `false`.
+    fn run<F: Fn(&SearchState) -> bool + Sync>(
+        &self,
+        board: &Board,
+        budget: &Budget,
+        stats: &mut SolverStats,
+        on_solution: F,
+    ) -> Result<(), BudgetExhausted> {
+        let Some(state) = SearchState::from_board(board) else { return Ok(()); };
+
+        let threads = self.threads.max(1);
+        let workers: Vec<Worker<Task>> = (0..threads).map(|_| Worker::new_lifo()).collect();
+        let pool = Pool {
+            injector: Injector::new(),
+            stealers: workers.iter().map(Worker::stealer).collect(),
+            pending: AtomicUsize::new(1),
+            stop: AtomicBool::new(false),
+            exhausted: AtomicBool::new(false),
+            budget,
+            on_solution: &on_solution,
+        };
+        pool.injector.push(Task { state, depth: 0 });
+
+        thread::scope(|scope| {
+            let handles: Vec<_> = workers.into_iter()
+                .map(|local| {
+                    let pool = &pool;
+                    scope.spawn(move || pool.work(local, self.split_depth))
+                })
+                .collect();
+            for handle in handles {
+                stats.merge(&handle.join().unwrap());
+            }
+        });
+
+        if pool.exhausted.into_inner() { return Err(BudgetExhausted); }
+        Ok(())
+    }
+}
+
+impl Solver for ParallelSolver {
+    fn search_solutions(
+        &self,
+        board: &Board,
+        limit: usize,
+        budget: &Budget,
+        stats: &mut SolverStats,
+    ) -> Result<Vec<Board>, BudgetExhausted> {
+        let solutions = Mutex::new(Vec::new());
+        if limit == 0 { return Ok(Vec::new()); }
+
+        self.run(board, budget, stats, |state| {
+            let mut solutions = solutions.lock().unwrap();
+            if solutions.len() < limit {
+                solutions.push(state.filled_board(board));
+            }
+            solutions.len() < limit
+        })?;
+        Ok(solutions.into_inner().unwrap())
+    }
+
+    fn search_count(
+        &self,
+        board: &Board,
+        limit: u64,
+        budget: &Budget,
+        stats: &mut SolverStats,
+    ) -> Result<u64, BudgetExhausted> {
+        let count = AtomicU64::new(0);
+        if limit == 0 { return Ok(0); }
+
+        self.run(board, budget, stats, |_| count.fetch_add(1, Ordering::Relaxed) + 1 < limit)?;
+        // workers racing past the limit may overshoot it
+        Ok(count.into_inner().min(limit))
+    }
+}
+
+#[cfg(test)]
+mod parallel_solver_tests {
+    use super::*;
+    use crate::{ evaluator, smart_solver };
+
+    #[test]
+    fn test_matches_sequential_counts() {
+        let puzzles = [
+            "872415369691783542435926781763258194258194637149637258...........................",
+            "...4.5.6...17.35..4.592...1.....8.94...1.4.3714....25.3.7.4..165..8..........1...",
+            "000000010400000000020000000000050407008000300001090000300400200050100000000806000",
+            "11...............................................................................",
+        ];
+        for puzzle in puzzles {
+            let board: Board = puzzle.parse().unwrap();
+            let expected = smart_solver::count_solutions(&board, false);
+            for threads in [1, 2, 4] {
+                for split_depth in [0, 1, 3] {
+                    let solver = ParallelSolver { threads, split_depth };
+                    assert_eq!(solver.count_solutions(&board, u64::MAX), expected);
+                    assert_eq!(solver.count_solutions(&board, 2), expected.min(2));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_collecting_solutions() {
+        let board: Board = "872415369691783542435926781763258194258194637149637258..........................."
+            .parse().unwrap();
+        let solver = ParallelSolver { threads: 4, split_depth: 2 };
+
+        let all = solver.collect_solutions(&board, usize::MAX);
+        assert_eq!(all.len(), 96);
+        assert!(all.iter().all(evaluator::evaluate));
+        let mut lines: Vec<String> = all.iter().map(Board::to_string).collect();
+        lines.sort();
+        lines.dedup();
+        assert_eq!(lines.len(), 96);
+
+        assert_eq!(solver.collect_solutions(&board, 5).len(), 5);
+    }
+}
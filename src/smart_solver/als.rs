@@ -0,0 +1,268 @@
+use std::{ collections::HashSet, ops::RangeInclusive };
+
+use crate::{
+    game::{ CandidateGrid, Candidates, Digit, Unit },
+    utilities,
+};
+
+use super::logic::{ AlmostLockedSet, Candidate, Reason, Step, Technique };
+
+// cells as bits of a mask, by position
+type Cells = u128;
+
+fn bit((r, c): (usize, usize)) -> Cells {
+    1 << (r * 9 + c)
+}
+
+fn mask(cells: impl IntoIterator<Item = (usize, usize)>) -> Cells {
+    cells.into_iter().fold(0, |mask, cell| mask | bit(cell))
+}
+
+fn cells_of(mask: Cells) -> impl Iterator<Item = (usize, usize)> {
+    (0..81).filter(move |&pos| mask >> pos & 1 == 1).map(utilities::coords_from_pos)
+}
+
+// an almost locked set with its cells as masks
+struct Set {
+    als: AlmostLockedSet,
+    cells: Cells,
+    // by digit, the cells of the set holding it
+    holding: [Cells; 9],
+    // by digit, the cells outside the set seeing all of those
+    seeing: [Cells; 9],
+}
+
+/// Every almost locked set of the grid, smallest first.
+fn almost_locked_sets(grid: &CandidateGrid) -> Vec<Set> {
+    let peers: [Cells; 81] = std::array::from_fn(|pos| {
+        let (r, c) = utilities::coords_from_pos(pos);
+        mask(utilities::peers(r, c))
+    });
+    let mut found = HashSet::new();
+    let mut sets = Vec::new();
+    for unit in Unit::all() {
+        let empty: Vec<(usize, usize)> = unit.cells().into_iter()
+            .filter(|&(r, c)| !grid.candidates(r, c).is_empty())
+            .collect();
+        for subset in 1..1u32 << empty.len() {
+            let cells: Vec<(usize, usize)> = empty.iter().enumerate()
+                .filter(|&(k, _)| subset >> k & 1 == 1)
+                .map(|(_, &cell)| cell)
+                .collect();
+            let digits: Candidates = cells.iter().flat_map(|&(r, c)| grid.candidates(r, c).iter()).collect();
+            let inside = mask(cells.iter().copied());
+            if digits.len() != cells.len() + 1 || !found.insert(inside) { continue; }
+
+            let mut holding = [0; 9];
+            let mut seeing = [0; 9];
+            for x in digits.iter() {
+                holding[x as usize] = mask(cells.iter().copied().filter(|&(r, c)| grid.candidates(r, c).contains(x)));
+                seeing[x as usize] = cells_of(holding[x as usize])
+                    .fold(!inside, |seen, (r, c)| seen & peers[r * 9 + c]);
+            }
+            sets.push(Set {
+                cells: inside,
+                als: AlmostLockedSet { unit, cells, digits },
+                holding,
+                seeing,
+            });
+        }
+    }
+    sets.sort_by_key(|set| set.als.cells.len());
+    sets
+}
+
+// Whether `x` can't be in both sets: both hold it, and every cell of
+// one holding it sees every cell of the other that does.
+fn restricted(a: &Set, b: &Set, x: Digit) -> bool {
+    let i = x as usize;
+    a.holding[i] != 0 && b.holding[i] != 0 && b.holding[i] & !a.seeing[i] == 0
+}
+
+/// Chains of disjoint almost locked sets, each joined to the next by a
+/// restricted common digit, different from the link before. Were the
+/// first set without a digit `z` they also hold, the first link would
+/// be locked in it and thus out of the second set, and so on down the
+/// chain, leaving `z` locked in the last set. Either way, cells seeing
+/// every `z` of the first and the last set can't hold it.
+fn als_chains(grid: &CandidateGrid, technique: Technique, lens: RangeInclusive<usize>) -> Option<Step> {
+    let sets = almost_locked_sets(grid);
+    let links: Vec<Vec<(usize, Digit)>> = sets.iter()
+        .map(|a| {
+            let mut links = Vec::new();
+            for (j, b) in sets.iter().enumerate() {
+                if a.cells & b.cells != 0 { continue; }
+                let common = Candidates::from_mask(a.als.digits.mask() & b.als.digits.mask());
+                links.extend(common.iter().filter(|&x| restricted(a, b, x)).map(|x| (j, x)));
+            }
+            links
+        })
+        .collect();
+
+    for len in lens {
+        for start in 0..sets.len() {
+            let mut chain = vec![start];
+            let mut digits = Vec::new();
+            if let Some(step) = extend(grid, technique, &sets, &links, len, &mut chain, &mut digits) {
+                return Some(step);
+            }
+        }
+    }
+    None
+}
+
+fn extend(
+    grid: &CandidateGrid,
+    technique: Technique,
+    sets: &[Set],
+    links: &[Vec<(usize, Digit)>],
+    len: usize,
+    chain: &mut Vec<usize>,
+    digits: &mut Vec<Digit>,
+) -> Option<Step> {
+    if chain.len() == len {
+        return conclude(grid, technique, sets, chain, digits);
+    }
+
+    let used = chain.iter().fold(0, |used, &i| used | sets[i].cells);
+    for &(next, x) in links[chain[chain.len() - 1]].iter() {
+        if sets[next].cells & used != 0 || digits.last() == Some(&x) { continue; }
+
+        chain.push(next);
+        digits.push(x);
+        if let Some(step) = extend(grid, technique, sets, links, len, chain, digits) {
+            return Some(step);
+        }
+        chain.pop();
+        digits.pop();
+    }
+    None
+}
+
+fn conclude(grid: &CandidateGrid, technique: Technique, sets: &[Set], chain: &[usize], links: &[Digit]) -> Option<Step> {
+    let (first, last) = (&sets[chain[0]], &sets[chain[chain.len() - 1]]);
+    let common = Candidates::from_mask(first.als.digits.mask() & last.als.digits.mask());
+    for z in common.iter().filter(|&z| z != links[0] && z != links[links.len() - 1]) {
+        let eliminations: Vec<Candidate> = cells_of(first.seeing[z as usize] & last.seeing[z as usize])
+            .filter(|&(r, c)| grid.candidates(r, c).contains(z))
+            .map(|cell| Candidate::new(cell, z))
+            .collect();
+        if eliminations.is_empty() { continue; }
+
+        let sets: Vec<AlmostLockedSet> = chain.iter().map(|&i| sets[i].als.clone()).collect();
+        return Some(Step {
+            technique,
+            placements: Vec::new(),
+            eliminations,
+            focus: sets.iter().flat_map(|set| set.cells.iter().copied()).collect(),
+            reason: Reason::AlmostLockedSets { sets, links: links.to_vec(), digit: z },
+        });
+    }
+    None
+}
+
+/// Two almost locked sets with a restricted common digit.
+pub(super) fn als_xz(grid: &CandidateGrid) -> Option<Step> {
+    als_chains(grid, Technique::AlsXz, 2..=2)
+}
+
+/// Two almost locked sets, each restricted to a third one on a
+/// different digit.
+pub(super) fn als_xy_wing(grid: &CandidateGrid) -> Option<Step> {
+    als_chains(grid, Technique::AlsXyWing, 3..=3)
+}
+
+/// Longer chains of almost locked sets, up to five of them.
+pub(super) fn als_chain(grid: &CandidateGrid) -> Option<Step> {
+    als_chains(grid, Technique::AlsChain, 4..=5)
+}
+
+#[cfg(test)]
+mod als_tests {
+    use super::*;
+    use crate::{
+        game::{ Board, Digit::* },
+        smart_solver::logic::{ logic_tests::{ assert_sound, set }, LogicalSolver },
+    };
+
+    const PUZZLE: &str = "........29.31..5...85.4.........9.7..4.8.6.1....32...6....6...58...9....2.7..3...";
+
+    #[test]
+    fn test_almost_locked_sets() {
+        let mut grid = CandidateGrid::from_board(&Board::zeroed());
+        set(&mut grid, (0, 0), &[D1, D5]);
+        set(&mut grid, (0, 1), &[D1, D2]);
+        set(&mut grid, (0, 2), &[D2, D5]);
+        let sets: Vec<AlmostLockedSet> = almost_locked_sets(&grid).into_iter().map(|set| set.als).collect();
+        // the three cells are locked, not almost; the rest are eight cells
+        // of a unit, holding all nine digits
+        assert_eq!(sets.iter().filter(|set| set.cells.len() < 8).count(), 6);
+        assert_eq!(sets[3], AlmostLockedSet {
+            unit: Unit::Row(0),
+            cells: vec![(0, 0), (0, 1)],
+            digits: [D1, D2, D5].into_iter().collect(),
+        });
+        assert_eq!(sets[3].to_string(), "(125)r1c1,r1c2");
+    }
+
+    #[test]
+    fn test_als_xz() {
+        let mut grid = CandidateGrid::from_board(&Board::zeroed());
+        set(&mut grid, (0, 0), &[D1, D5]);
+        set(&mut grid, (4, 0), &[D1, D3]);
+        set(&mut grid, (4, 4), &[D3, D5]);
+        let step = als_xz(&grid).unwrap();
+        assert_eq!(step.eliminations, vec![Candidate::new((0, 4), D5)]);
+        assert_eq!(step.to_string(), "ALS-XZ: (15)r1c1 -1- (135)r5c1,r5c5, so 5 is in the first set or the last; r1c5<>5.");
+    }
+
+    #[test]
+    fn test_als_xy_wing() {
+        let mut grid = CandidateGrid::from_board(&Board::zeroed());
+        set(&mut grid, (0, 0), &[D1, D5]);
+        set(&mut grid, (0, 4), &[D1, D2]);
+        set(&mut grid, (4, 4), &[D2, D5]);
+        let step = als_xy_wing(&grid).unwrap();
+        assert_eq!(step.reason, Reason::AlmostLockedSets {
+            sets: vec![
+                AlmostLockedSet { unit: Unit::Row(0), cells: vec![(0, 0)], digits: [D1, D5].into_iter().collect() },
+                AlmostLockedSet { unit: Unit::Row(0), cells: vec![(0, 4)], digits: [D1, D2].into_iter().collect() },
+                AlmostLockedSet { unit: Unit::Row(4), cells: vec![(4, 4)], digits: [D2, D5].into_iter().collect() },
+            ],
+            links: vec![D1, D2],
+            digit: D5,
+        });
+        assert_eq!(step.eliminations, vec![Candidate::new((4, 0), D5)]);
+    }
+
+    #[test]
+    fn test_als_chain() {
+        let mut grid = CandidateGrid::from_board(&Board::zeroed());
+        set(&mut grid, (0, 0), &[D1, D5]);
+        set(&mut grid, (0, 4), &[D1, D2]);
+        set(&mut grid, (4, 4), &[D2, D3]);
+        set(&mut grid, (4, 8), &[D3, D5]);
+        let step = als_chain(&grid).unwrap();
+        assert_eq!(
+            step.to_string(),
+            "ALS chain: (15)r1c1 -1- (12)r1c5 -2- (23)r5c5 -3- (35)r5c9, \
+             so 5 is in the first set or the last; r1c9<>5, r5c1<>5."
+        );
+    }
+
+    #[test]
+    fn test_solving_with_als() {
+        let board: Board = PUZZLE.parse().unwrap();
+        let without: Vec<Technique> = Technique::ALL.iter().copied()
+            .filter(|t| !t.assumes_uniqueness() && t.difficulty() <= Technique::Aic.difficulty())
+            .collect();
+        assert!(!LogicalSolver::with_techniques(&without).solve(&board).is_solved());
+
+        let solution = LogicalSolver::default().solve(&board);
+        assert!(solution.is_solved());
+        assert_eq!(solution.hardest(), Some(Technique::AlsChain));
+        assert!(solution.steps.iter().any(|step| step.technique == Technique::AlsXyWing));
+        assert_sound(&board, &solution.steps);
+    }
+
+}
@@ -0,0 +1,357 @@
+use std::collections::VecDeque;
+
+use crate::{
+    game,
+    game::{ CandidateGrid, Unit },
+    utilities,
+};
+
+use super::logic::{ seen_by_all, Candidate, Chain, Reason, Step, Technique };
+
+// the links a chain may use
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Links {
+    // conjugate pairs, and peers holding the digit
+    SingleDigit,
+    // bivalue cells, and peers holding the same digit
+    Bivalue,
+    // all of the above, and any two digits of a cell
+    All,
+}
+
+fn index(candidate: Candidate) -> usize {
+    let (r, c) = candidate.cell;
+    (r * 9 + c) * 9 + candidate.digit as usize
+}
+
+fn candidate(i: usize) -> Candidate {
+    Candidate::new(utilities::coords_from_pos(i / 9), game::LEGAL_VALUES[i % 9])
+}
+
+/// Links between the candidates of a grid, by index: of two strongly
+/// linked candidates one is true, of two weakly linked ones at most one.
+struct LinkGraph {
+    strong: Vec<Vec<usize>>,
+    weak: Vec<Vec<usize>>,
+}
+
+impl LinkGraph {
+    fn new(grid: &CandidateGrid, links: Links) -> Self {
+        let mut graph = LinkGraph { strong: vec![Vec::new(); 729], weak: vec![Vec::new(); 729] };
+        for pos in 0..81 {
+            let cell = utilities::coords_from_pos(pos);
+            let digits = grid.candidates(cell.0, cell.1);
+            for x in digits.iter() {
+                let from = index(Candidate::new(cell, x));
+                if links != Links::SingleDigit {
+                    for y in digits.iter().filter(|&y| y != x) {
+                        let to = index(Candidate::new(cell, y));
+                        if digits.len() == 2 { graph.strong[from].push(to); }
+                        if links == Links::All { graph.weak[from].push(to); }
+                    }
+                }
+                if links != Links::Bivalue {
+                    for unit in Unit::all().filter(|unit| unit.contains(cell)) {
+                        let [a, b] = grid.cells_with(unit, x)[..] else { continue; };
+                        let to = index(Candidate::new(if a == cell { b } else { a }, x));
+                        if !graph.strong[from].contains(&to) { graph.strong[from].push(to); }
+                    }
+                }
+                for (r, c) in utilities::peers(cell.0, cell.1) {
+                    if grid.candidates(r, c).contains(x) {
+                        graph.weak[from].push(index(Candidate::new((r, c), x)));
+                    }
+                }
+            }
+        }
+        graph
+    }
+
+    // Chains from `start` assumed false, breadth first so that the
+    // shortest come first, handed to `conclude` whenever they reach a
+    // candidate that has to be true. Stops at the first step it gives,
+    // or at chains of `max_len` candidates.
+    fn search(
+        &self,
+        start: usize,
+        max_len: usize,
+        conclude: &impl Fn(&[Candidate]) -> Option<Step>,
+    ) -> Option<(usize, Step)> {
+        // a candidate may be reached once false (even depth) and once true
+        let mut parent = [vec![None; 729], vec![None; 729]];
+        let mut queue = VecDeque::from([(start, 0)]);
+        parent[0][start] = Some(start);
+
+        while let Some((i, depth)) = queue.pop_front() {
+            if depth + 2 > max_len { break; }
+            let on = depth % 2 == 1;
+            let next = if on { &self.weak[i] } else { &self.strong[i] };
+            for &j in next {
+                let parity = 1 - depth % 2;
+                if parent[parity][j].is_some() && !(j == start && parity == 1) { continue; }
+                parent[parity][j] = Some(i);
+
+                if parity == 1 {
+                    let chain = Self::path(&parent, j, depth + 1);
+                    let simple = chain.iter().enumerate().all(|(k, x)| !chain[k + 1..].contains(x) || k == 0);
+                    if simple && let Some(step) = conclude(&chain) {
+                        return Some((chain.len(), step));
+                    }
+                }
+                if j != start { queue.push_back((j, depth + 1)); }
+            }
+        }
+        None
+    }
+
+    fn path(parent: &[Vec<Option<usize>>; 2], end: usize, depth: usize) -> Vec<Candidate> {
+        let mut chain = vec![candidate(end)];
+        let mut i = end;
+        for d in (1..=depth).rev() {
+            i = parent[d % 2][i].unwrap();
+            chain.push(candidate(i));
+        }
+        chain.reverse();
+        chain
+    }
+
+    fn weakly_linked(&self, a: Candidate, b: Candidate) -> bool {
+        self.weak[index(a)].contains(&index(b))
+    }
+}
+
+// the shortest chain over `links` that `conclude` draws a step from
+fn shortest(grid: &CandidateGrid, links: Links, conclude: impl Fn(&LinkGraph, &[Candidate]) -> Option<Step>) -> Option<Step> {
+    let graph = LinkGraph::new(grid, links);
+    let conclude = |chain: &[Candidate]| conclude(&graph, chain);
+    let mut best: Option<(usize, Step)> = None;
+    for start in (0..729).filter(|&i| !graph.strong[i].is_empty()) {
+        let max_len = best.as_ref().map_or(usize::MAX, |(len, _)| len - 1);
+        if let Some(found) = graph.search(start, max_len, &conclude) {
+            best = Some(found);
+        }
+    }
+    best.map(|(_, step)| step)
+}
+
+fn chain_step(
+    technique: Technique,
+    chain: Vec<Candidate>,
+    placements: Vec<Candidate>,
+    eliminations: Vec<Candidate>,
+) -> Option<Step> {
+    if placements.is_empty() && eliminations.is_empty() { return None; }
+
+    let mut focus = Vec::new();
+    for cell in chain.iter().map(|x| x.cell) {
+        if !focus.contains(&cell) { focus.push(cell); }
+    }
+    Some(Step { technique, placements, eliminations, focus, reason: Reason::Chain { chain: Chain(chain) } })
+}
+
+fn present(grid: &CandidateGrid, candidates: impl IntoIterator<Item = Candidate>) -> Vec<Candidate> {
+    candidates.into_iter()
+        .filter(|x| grid.candidates(x.cell.0, x.cell.1).contains(x.digit))
+        .collect()
+}
+
+// One end of the chain is true: with a digit in common, the cells seeing
+// both lose it; with two digits, each end loses the other's digit when
+// they see each other, in an AIC. Ends in one cell are left to
+// discontinuous loops.
+fn ends_step(grid: &CandidateGrid, technique: Technique, chain: &[Candidate]) -> Option<Step> {
+    let (a, b) = (chain[0], chain[chain.len() - 1]);
+    if a.cell == b.cell { return None; }
+
+    let eliminations = if a.digit == b.digit {
+        seen_by_all(grid, &[a.cell, b.cell], a.digit)
+    } else if technique == Technique::Aic && utilities::are_peers(a.cell, b.cell) {
+        present(grid, [Candidate::new(a.cell, b.digit), Candidate::new(b.cell, a.digit)])
+    } else {
+        Vec::new()
+    };
+    chain_step(technique, chain.to_vec(), Vec::new(), eliminations)
+}
+
+/// Chains of strong and weak links on a single digit.
+pub(super) fn x_chain(grid: &CandidateGrid) -> Option<Step> {
+    shortest(grid, Links::SingleDigit, |_, chain| ends_step(grid, Technique::XChain, chain))
+}
+
+/// Chains of bivalue cells, each sharing a digit with the next, with the
+/// same digit at both ends.
+pub(super) fn xy_chain(grid: &CandidateGrid) -> Option<Step> {
+    shortest(grid, Links::Bivalue, |_, chain| ends_step(grid, Technique::XYChain, chain))
+}
+
+/// Chains mixing any kind of link.
+pub(super) fn aic(grid: &CandidateGrid) -> Option<Step> {
+    shortest(grid, Links::All, |_, chain| ends_step(grid, Technique::Aic, chain))
+}
+
+/// A chain whose last candidate is weakly linked to its first: the loop
+/// can be read both ways round, so every weak link in it is strong too.
+/// A digit then goes nowhere that sees both cells of a weak link in a
+/// unit, and a cell with a weak link inside holds nothing else.
+pub(super) fn continuous_nice_loop(grid: &CandidateGrid) -> Option<Step> {
+    shortest(grid, Links::All, |graph, chain| {
+        let (first, last) = (chain[0], chain[chain.len() - 1]);
+        if chain.len() < 4 || first == last || !graph.weakly_linked(last, first) { return None; }
+
+        let mut looped = chain.to_vec();
+        looped.push(first);
+        let mut eliminations = Vec::new();
+        for pair in looped[1..].chunks(2) {
+            let [a, b] = pair else { continue; };
+            let found = if a.cell == b.cell {
+                let (r, c) = a.cell;
+                grid.candidates(r, c).iter()
+                    .filter(|&x| x != a.digit && x != b.digit)
+                    .map(|x| Candidate::new(a.cell, x))
+                    .collect()
+            } else {
+                seen_by_all(grid, &[a.cell, b.cell], a.digit)
+            };
+            for x in found {
+                if !looped.contains(&x) && !eliminations.contains(&x) { eliminations.push(x); }
+            }
+        }
+        chain_step(Technique::ContinuousNiceLoop, looped, Vec::new(), eliminations)
+    })
+}
+
+/// A chain back to where it started. From a candidate to itself, it's
+/// true, since it being false would make it true. Between two digits of
+/// a cell, one of them is, and the cell holds nothing else.
+pub(super) fn discontinuous_nice_loop(grid: &CandidateGrid) -> Option<Step> {
+    shortest(grid, Links::All, |_, chain| {
+        let (first, last) = (chain[0], chain[chain.len() - 1]);
+        if first == last {
+            chain_step(Technique::DiscontinuousNiceLoop, chain.to_vec(), vec![first], Vec::new())
+        } else if first.cell == last.cell {
+            let (r, c) = first.cell;
+            let eliminations = grid.candidates(r, c).iter()
+                .filter(|&x| x != first.digit && x != last.digit)
+                .map(|x| Candidate::new(first.cell, x))
+                .collect();
+            chain_step(Technique::DiscontinuousNiceLoop, chain.to_vec(), Vec::new(), eliminations)
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(test)]
+mod chains_tests {
+    use super::*;
+    use crate::{
+        game::{ Board, Digit::* },
+        smart_solver::logic::{ logic_tests::{ assert_sound, restrict, set }, LogicalSolver },
+    };
+
+    const PUZZLE: &str = "7..1............34..2...69....8.53.........764...96.15.5...8...1.42......3.91....";
+
+    // 1 only in two cells of columns 1, 5 and 8, linked through rows 2, 5 and 8
+    fn x_loop() -> CandidateGrid {
+        let mut grid = CandidateGrid::from_board(&Board::zeroed());
+        restrict(&mut grid, D1, Unit::Col(0), &[(1, 0), (4, 0)]);
+        restrict(&mut grid, D1, Unit::Col(4), &[(4, 4), (7, 4)]);
+        restrict(&mut grid, D1, Unit::Col(7), &[(7, 7), (1, 7)]);
+        grid
+    }
+
+    #[test]
+    fn test_x_chain() {
+        let grid = x_loop();
+        let step = x_chain(&grid).unwrap();
+        let chain = [(1, 0), (4, 0), (4, 4), (7, 4), (7, 7), (1, 7)].map(|cell| Candidate::new(cell, D1));
+        assert_eq!(step.reason, Reason::Chain { chain: Chain(chain.to_vec()) });
+        assert_eq!(step.eliminations, [1, 2, 3, 5, 6, 8].map(|c| Candidate::new((1, c), D1)));
+        assert!(step.to_string().starts_with(
+            "X-Chain: (1)r2c1=(1)r5c1-(1)r5c5=(1)r8c5-(1)r8c8=(1)r2c8, so 1r2c1 or 1r2c8 is true; r2c2<>1,"
+        ));
+    }
+
+    #[test]
+    fn test_xy_chain() {
+        let mut grid = CandidateGrid::from_board(&Board::zeroed());
+        set(&mut grid, (0, 0), &[D1, D2]);
+        set(&mut grid, (0, 5), &[D2, D3]);
+        set(&mut grid, (4, 5), &[D3, D4]);
+        set(&mut grid, (4, 8), &[D4, D1]);
+        let step = xy_chain(&grid).unwrap();
+        assert_eq!(step.eliminations, vec![Candidate::new((0, 8), D1), Candidate::new((4, 0), D1)]);
+        assert_eq!(
+            step.to_string(),
+            "XY-Chain: (1=2)r1c1-(2=3)r1c6-(3=4)r5c6-(4=1)r5c9, so 1r1c1 or 1r5c9 is true; r1c9<>1, r5c1<>1."
+        );
+    }
+
+    #[test]
+    fn test_aic() {
+        let mut grid = CandidateGrid::from_board(&Board::zeroed());
+        restrict(&mut grid, D1, Unit::Row(0), &[(0, 0), (0, 4)]);
+        set(&mut grid, (3, 4), &[D1, D2]);
+        set(&mut grid, (3, 0), &[D2, D3]);
+        assert!(xy_chain(&grid).is_none());
+        let step = aic(&grid).unwrap();
+        assert_eq!(step.eliminations, vec![Candidate::new((0, 0), D3)]);
+        assert_eq!(
+            step.to_string(),
+            "AIC: (1)r1c1=(1)r1c5-(1=2)r4c5-(2=3)r4c1, so 1r1c1 or 3r4c1 is true; r1c1<>3."
+        );
+    }
+
+    #[test]
+    fn test_continuous_nice_loop() {
+        let grid = x_loop();
+        let step = continuous_nice_loop(&grid).unwrap();
+        assert!(step.to_string().starts_with(
+            "Continuous nice loop: (1)r2c1=(1)r5c1-(1)r5c5=(1)r8c5-(1)r8c8=(1)r2c8-(1)r2c1, \
+             a loop, so every weak link in it is strong as well;"
+        ));
+        // 1 leaves rows 2, 5 and 8 outside the loop
+        assert_eq!(step.eliminations.len(), 18);
+        assert!(step.eliminations.iter().all(|x| [1, 4, 7].contains(&x.cell.0) && ![0, 4, 7].contains(&x.cell.1)));
+    }
+
+    #[test]
+    fn test_discontinuous_nice_loop() {
+        let mut grid = CandidateGrid::from_board(&Board::zeroed());
+        set(&mut grid, (0, 0), &[D1, D2]);
+        set(&mut grid, (0, 4), &[D2, D3]);
+        set(&mut grid, (4, 4), &[D3, D1]);
+        restrict(&mut grid, D1, Unit::Col(0), &[(0, 0), (4, 0)]);
+        let step = discontinuous_nice_loop(&grid).unwrap();
+        assert_eq!(step.placements, vec![Candidate::new((0, 0), D1)]);
+        assert_eq!(
+            step.to_string(),
+            "Discontinuous nice loop: (1=2)r1c1-(2=3)r1c5-(3=1)r5c5-(1)r5c1=(1)r1c1, so r1c1 is 1."
+        );
+    }
+
+    #[test]
+    fn test_solving_with_chains() {
+        let board: Board = PUZZLE.parse().unwrap();
+        let without: Vec<Technique> = Technique::ALL.iter().copied()
+            .filter(|t| !t.assumes_uniqueness() && t.difficulty() <= Technique::FinnedJellyfish.difficulty())
+            .collect();
+        assert!(!LogicalSolver::with_techniques(&without).solve(&board).is_solved());
+
+        let chains = [
+            Technique::XChain,
+            Technique::XYChain,
+            Technique::ContinuousNiceLoop,
+            Technique::DiscontinuousNiceLoop,
+            Technique::Aic,
+        ];
+        let solution = LogicalSolver::with_techniques(&[&without[..], &chains[..]].concat()).solve(&board);
+        assert!(solution.is_solved());
+        assert!(solution.steps.iter().any(|step| matches!(
+            step.technique,
+            Technique::XChain | Technique::XYChain | Technique::Aic
+        )));
+        assert_sound(&board, &solution.steps);
+    }
+
+}
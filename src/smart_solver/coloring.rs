@@ -0,0 +1,213 @@
+use crate::{
+    game,
+    game::{ CandidateGrid, Digit },
+    utilities,
+};
+
+use super::{
+    logic::{ Candidate, ColoringRule, Reason, Step, Technique },
+    single_digit::strong_links,
+};
+
+type Colors = [Vec<(usize, usize)>; 2];
+
+// the conjugate pairs of `digit` joined into clusters, each one split in
+// two colors so that every pair has one cell of each
+fn clusters(grid: &CandidateGrid, digit: Digit) -> Vec<Colors> {
+    let links = strong_links(grid, digit);
+    let mut colored = [[false; 9]; 9];
+    let mut clusters: Vec<Colors> = Vec::new();
+
+    for link in links.iter() {
+        let (r, c) = link.cells[0];
+        if colored[r][c] { continue; }
+
+        let mut cluster: Colors = [Vec::new(), Vec::new()];
+        let mut queue = vec![(link.cells[0], 0)];
+        colored[r][c] = true;
+        while let Some((cell, k)) = queue.pop() {
+            cluster[k].push(cell);
+            for other in links.iter() {
+                let Some(i) = other.cells.iter().position(|&x| x == cell) else { continue; };
+                let (s, t) = other.cells[1 - i];
+                if !colored[s][t] {
+                    colored[s][t] = true;
+                    queue.push(((s, t), 1 - k));
+                }
+            }
+        }
+        cluster[0].sort();
+        cluster[1].sort();
+        clusters.push(cluster);
+    }
+    clusters
+}
+
+fn sees_any(cell: (usize, usize), cells: &[(usize, usize)]) -> bool {
+    cells.iter().any(|&other| utilities::are_peers(cell, other))
+}
+
+fn in_clusters(cell: (usize, usize), clusters: &[&Colors]) -> bool {
+    clusters.iter().any(|colors| colors[0].contains(&cell) || colors[1].contains(&cell))
+}
+
+// candidates for `digit` outside the clusters that see both groups
+fn seeing_both(
+    grid: &CandidateGrid,
+    digit: Digit,
+    first: &[(usize, usize)],
+    second: &[(usize, usize)],
+    clusters: &[&Colors],
+) -> Vec<Candidate> {
+    (0..81)
+        .map(utilities::coords_from_pos)
+        .filter(|&(r, c)| grid.candidates(r, c).contains(digit))
+        .filter(|&cell| !in_clusters(cell, clusters))
+        .filter(|&cell| sees_any(cell, first) && sees_any(cell, second))
+        .map(|cell| Candidate::new(cell, digit))
+        .collect()
+}
+
+fn coloring_step(
+    technique: Technique,
+    digit: Digit,
+    clusters: Vec<Colors>,
+    rule: ColoringRule,
+    eliminations: Vec<Candidate>,
+) -> Step {
+    Step {
+        technique,
+        placements: Vec::new(),
+        eliminations,
+        focus: clusters.iter().flat_map(|colors| colors.iter().flatten().copied()).collect(),
+        reason: Reason::Coloring { digit, clusters, rule },
+    }
+}
+
+/// Colors the conjugate pairs of a single cluster. When two cells of one
+/// color see each other, that color is false (color wrap); otherwise a
+/// cell seeing both colors can't hold the digit (color trap).
+pub(super) fn simple_coloring(grid: &CandidateGrid) -> Option<Step> {
+    for digit in game::LEGAL_VALUES {
+        for colors in clusters(grid, digit) {
+            for k in 0..2 {
+                let wrapped = colors[k].iter().any(|&cell| sees_any(cell, &colors[k]));
+                if !wrapped { continue; }
+
+                let eliminations = colors[k].iter().map(|&cell| Candidate::new(cell, digit)).collect();
+                let colors = [colors[k].clone(), colors[1 - k].clone()];
+                return Some(coloring_step(Technique::SimpleColoring, digit, vec![colors], ColoringRule::Wrap, eliminations));
+            }
+
+            let eliminations = seeing_both(grid, digit, &colors[0], &colors[1], &[&colors]);
+            if !eliminations.is_empty() {
+                return Some(coloring_step(Technique::SimpleColoring, digit, vec![colors], ColoringRule::Trap, eliminations));
+            }
+        }
+    }
+    None
+}
+
+/// Colors several clusters at once. When a cell of one cluster's color
+/// sees a cell of another's, one of the two opposite colors is true, and
+/// cells seeing both of those can't hold the digit.
+pub(super) fn multi_coloring(grid: &CandidateGrid) -> Option<Step> {
+    for digit in game::LEGAL_VALUES {
+        let clusters = clusters(grid, digit);
+        for (i, first) in clusters.iter().enumerate() {
+            for second in clusters[i + 1..].iter() {
+                for (a, b) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+                    if !first[a].iter().any(|&cell| sees_any(cell, &second[b])) { continue; }
+
+                    let eliminations = seeing_both(grid, digit, &first[1 - a], &second[1 - b], &[first, second]);
+                    if eliminations.is_empty() { continue; }
+
+                    let linked = vec![
+                        [first[a].clone(), first[1 - a].clone()],
+                        [second[b].clone(), second[1 - b].clone()],
+                    ];
+                    return Some(coloring_step(Technique::MultiColoring, digit, linked, ColoringRule::Linked, eliminations));
+                }
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod coloring_tests {
+    use super::*;
+    use crate::{
+        game::{ Board, Digit::*, Unit },
+        smart_solver::logic::{ logic_tests::{ assert_sound, restrict }, LogicalSolver },
+    };
+
+    const PUZZLE: &str = "....48.7.51...9..4.9.............1...5.38...2.....54..3.......192.6..7..1...7.6..";
+
+    #[test]
+    fn test_color_trap() {
+        let mut grid = CandidateGrid::from_board(&Board::zeroed());
+        restrict(&mut grid, D3, Unit::Row(0), &[(0, 0), (0, 4)]);
+        restrict(&mut grid, D3, Unit::Col(4), &[(0, 4), (4, 4)]);
+        restrict(&mut grid, D3, Unit::Box(4), &[(4, 4), (3, 3)]);
+        let step = simple_coloring(&grid).unwrap();
+        assert_eq!(step.reason, Reason::Coloring {
+            digit: D3,
+            clusters: vec![[vec![(0, 0), (4, 4)], vec![(0, 4), (3, 3)]]],
+            rule: ColoringRule::Trap,
+        });
+        assert_eq!(step.eliminations, vec![Candidate::new((3, 0), D3)]);
+        assert_eq!(
+            step.to_string(),
+            "Simple coloring: 3 colored r1c1,r5c5 | r1c5,r4c4, one of the two colors is true; r4c1<>3."
+        );
+    }
+
+    #[test]
+    fn test_color_wrap() {
+        let mut grid = CandidateGrid::from_board(&Board::zeroed());
+        restrict(&mut grid, D2, Unit::Row(0), &[(0, 0), (0, 4)]);
+        restrict(&mut grid, D2, Unit::Col(4), &[(0, 4), (4, 4)]);
+        restrict(&mut grid, D2, Unit::Row(4), &[(4, 4), (4, 1)]);
+        restrict(&mut grid, D2, Unit::Col(1), &[(4, 1), (1, 1)]);
+        let step = simple_coloring(&grid).unwrap();
+        assert_eq!(step.reason, Reason::Coloring {
+            digit: D2,
+            clusters: vec![[vec![(0, 0), (1, 1), (4, 4)], vec![(0, 4), (4, 1)]]],
+            rule: ColoringRule::Wrap,
+        });
+        assert_eq!(step.eliminations, [(0, 0), (1, 1), (4, 4)].map(|cell| Candidate::new(cell, D2)));
+        assert!(step.to_string().starts_with(
+            "Simple coloring: 2 colored r1c1,r2c2,r5c5 | r1c5,r5c2, two cells of the first color see each other;"
+        ));
+    }
+
+    #[test]
+    fn test_multi_coloring() {
+        let mut grid = CandidateGrid::from_board(&Board::zeroed());
+        restrict(&mut grid, D8, Unit::Row(0), &[(0, 0), (0, 8)]);
+        restrict(&mut grid, D8, Unit::Row(2), &[(2, 1), (2, 7)]);
+        assert!(simple_coloring(&grid).is_none());
+        let step = multi_coloring(&grid).unwrap();
+        assert_eq!(step.reason, Reason::Coloring {
+            digit: D8,
+            clusters: vec![[vec![(0, 0)], vec![(0, 8)]], [vec![(2, 1)], vec![(2, 7)]]],
+            rule: ColoringRule::Linked,
+        });
+        assert_eq!(step.eliminations, [(1, 6), (1, 7), (1, 8)].map(|cell| Candidate::new(cell, D8)));
+        assert!(step.to_string().starts_with(
+            "Multi-coloring: 8 colored r1c1 | r1c9 and r3c2 | r3c8, the first colors see each other, \
+             so one of the second ones is true;"
+        ));
+    }
+
+    #[test]
+    fn test_solving_with_coloring() {
+        let board: Board = PUZZLE.parse().unwrap();
+        let solution = LogicalSolver::default().solve(&board);
+        assert!(solution.is_solved());
+        assert_eq!(solution.hardest(), Some(Technique::MultiColoring));
+        assert!(solution.steps.iter().any(|step| step.technique == Technique::SimpleColoring));
+        assert_sound(&board, &solution.steps);
+    }
+}
@@ -0,0 +1,259 @@
+use crate::{
+    game,
+    game::{ CandidateGrid, Digit, Unit },
+    utilities,
+};
+
+use super::logic::{ combinations, Candidate, Reason, Step, Technique };
+
+fn technique(size: usize, finned: bool) -> Technique {
+    match (size, finned) {
+        (2, false) => Technique::XWing,
+        (3, false) => Technique::Swordfish,
+        (_, false) => Technique::Jellyfish,
+        (2, true) => Technique::FinnedXWing,
+        (3, true) => Technique::FinnedSwordfish,
+        (_, true) => Technique::FinnedJellyfish,
+    }
+}
+
+// rows as base sets and columns as cover sets, or the other way round
+#[derive(Copy, Clone)]
+struct Orientation {
+    rows: bool,
+}
+
+impl Orientation {
+    fn base(self, i: usize) -> Unit {
+        if self.rows { Unit::Row(i) } else { Unit::Col(i) }
+    }
+
+    fn cover(self, j: usize) -> Unit {
+        if self.rows { Unit::Col(j) } else { Unit::Row(j) }
+    }
+
+    fn cell(self, i: usize, j: usize) -> (usize, usize) {
+        if self.rows { (i, j) } else { (j, i) }
+    }
+
+    // for each base line, a mask of the cover lines where `digit` is
+    // still a candidate
+    fn positions(self, grid: &CandidateGrid, digit: Digit) -> [u16; 9] {
+        std::array::from_fn(|i| {
+            (0..9)
+                .filter(|&j| {
+                    let (r, c) = self.cell(i, j);
+                    grid.candidates(r, c).contains(digit)
+                })
+                .fold(0, |mask, j| mask | 1 << j)
+        })
+    }
+}
+
+const ORIENTATIONS: [Orientation; 2] = [Orientation { rows: true }, Orientation { rows: false }];
+
+fn lines_of(mask: u16) -> Vec<usize> {
+    (0..9).filter(|&j| mask & 1 << j != 0).collect()
+}
+
+// candidates of the cover lines outside the base lines
+fn outside_base(orientation: Orientation, positions: &[u16; 9], base: &[usize], cover: &[usize]) -> Vec<(usize, usize)> {
+    let mut cells = Vec::new();
+    for &j in cover {
+        for i in (0..9).filter(|i| !base.contains(i) && positions[*i] & 1 << j != 0) {
+            cells.push(orientation.cell(i, j));
+        }
+    }
+    cells
+}
+
+fn step(
+    digit: Digit,
+    orientation: Orientation,
+    base: &[usize],
+    cover: &[usize],
+    fins: Vec<(usize, usize)>,
+    sashimi: bool,
+    eliminations: Vec<Candidate>,
+) -> Step {
+    let mut focus: Vec<(usize, usize)> = base.iter()
+        .flat_map(|&i| cover.iter().map(move |&j| orientation.cell(i, j)))
+        .collect();
+    focus.extend(fins.iter().copied());
+    Step {
+        technique: technique(base.len(), !fins.is_empty()),
+        placements: Vec::new(),
+        eliminations,
+        focus,
+        reason: Reason::Fish {
+            digit,
+            base: base.iter().map(|&i| orientation.base(i)).collect(),
+            cover: cover.iter().map(|&j| orientation.cover(j)).collect(),
+            fins,
+            sashimi,
+        },
+    }
+}
+
+/// `size` rows whose candidates for a digit all lie in `size` columns, or
+/// the other way round: the digit goes nowhere else in those columns.
+pub(super) fn basic_fish(grid: &CandidateGrid, size: usize) -> Option<Step> {
+    for digit in game::LEGAL_VALUES {
+        for orientation in ORIENTATIONS {
+            let positions = orientation.positions(grid, digit);
+            let lines: Vec<usize> = (0..9)
+                .filter(|&i| (2..=size as u32).contains(&positions[i].count_ones()))
+                .collect();
+            for base in combinations(&lines, size) {
+                let union = base.iter().fold(0, |mask, &i| mask | positions[i]);
+                if union.count_ones() as usize != size { continue; }
+
+                let cover = lines_of(union);
+                let eliminations: Vec<Candidate> = outside_base(orientation, &positions, &base, &cover).into_iter()
+                    .map(|cell| Candidate::new(cell, digit))
+                    .collect();
+                if eliminations.is_empty() { continue; }
+
+                return Some(step(digit, orientation, &base, &cover, Vec::new(), false, eliminations));
+            }
+        }
+    }
+    None
+}
+
+/// A fish with extra candidates, the fins, in the base sets. The fins
+/// all share a box, so either the fish holds or a fin is true, and the
+/// digit goes nowhere else in the cover sets within that box. The fish
+/// is sashimi when a base set has a single candidate left besides fins.
+pub(super) fn finned_fish(grid: &CandidateGrid, size: usize) -> Option<Step> {
+    for digit in game::LEGAL_VALUES {
+        for orientation in ORIENTATIONS {
+            let positions = orientation.positions(grid, digit);
+            let lines: Vec<usize> = (0..9).filter(|&i| positions[i] != 0).collect();
+            for base in combinations(&lines, size) {
+                let union = base.iter().fold(0, |mask, &i| mask | positions[i]);
+                if union.count_ones() as usize <= size { continue; }
+
+                for cover in combinations(&lines_of(union), size) {
+                    let cover_mask = cover.iter().fold(0, |mask, &j| mask | 1 << j);
+                    // every base set has to keep part of the fish
+                    if base.iter().any(|&i| positions[i] & cover_mask == 0) { continue; }
+
+                    let fins: Vec<(usize, usize)> = base.iter()
+                        .flat_map(|&i| lines_of(positions[i] & !cover_mask).into_iter()
+                            .map(move |j| orientation.cell(i, j)))
+                        .collect();
+                    let fin_box = utilities::square_limits_from_cell(fins[0].0, fins[0].1);
+                    if fins.iter().any(|&(r, c)| utilities::square_limits_from_cell(r, c) != fin_box) { continue; }
+
+                    let eliminations: Vec<Candidate> = outside_base(orientation, &positions, &base, &cover).into_iter()
+                        .filter(|&(r, c)| utilities::square_limits_from_cell(r, c) == fin_box)
+                        .map(|cell| Candidate::new(cell, digit))
+                        .collect();
+                    if eliminations.is_empty() { continue; }
+
+                    let sashimi = base.iter().any(|&i| (positions[i] & cover_mask).count_ones() == 1);
+                    return Some(step(digit, orientation, &base, &cover, fins, sashimi, eliminations));
+                }
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod fish_tests {
+    use super::*;
+    use crate::{
+        game::{ Board, Digit::* },
+        smart_solver::logic::{ logic_tests::assert_sound, LogicalSolver },
+    };
+
+    const PUZZLE: &str = ".......3..5...82.19.1.3...55.71.6.....38.....2....387....3....4....7.15..3.2..7..";
+
+    // keeps `digit` only in the given columns of the given rows
+    fn restrict(grid: &mut CandidateGrid, digit: Digit, rows: &[usize], cols: &[usize]) {
+        for &r in rows {
+            for c in (0..9).filter(|c| !cols.contains(c)) {
+                grid.remove_candidate(r, c, digit);
+            }
+        }
+    }
+
+    #[test]
+    fn test_x_wing() {
+        let mut grid = CandidateGrid::from_board(&Board::zeroed());
+        restrict(&mut grid, D4, &[1, 6], &[2, 7]);
+        let step = basic_fish(&grid, 2).unwrap();
+        assert_eq!(step.technique, Technique::XWing);
+        assert_eq!(step.reason, Reason::Fish {
+            digit: D4,
+            base: vec![Unit::Row(1), Unit::Row(6)],
+            cover: vec![Unit::Col(2), Unit::Col(7)],
+            fins: Vec::new(),
+            sashimi: false,
+        });
+        // the 7 other cells of both columns
+        assert_eq!(step.eliminations.len(), 14);
+        assert!(step.to_string().starts_with("X-Wing: in rows 2,7, 4 can only go in columns 3,8; r1c3<>4, "));
+    }
+
+    #[test]
+    fn test_swordfish() {
+        let mut grid = CandidateGrid::from_board(&Board::zeroed());
+        restrict(&mut grid, D6, &[0, 4, 8], &[1, 5, 7]);
+        grid.remove_candidate(0, 1, D6);
+        grid.remove_candidate(4, 5, D6);
+        grid.remove_candidate(8, 7, D6);
+        assert!(basic_fish(&grid, 2).is_none());
+        let step = basic_fish(&grid, 3).unwrap();
+        assert_eq!(step.technique, Technique::Swordfish);
+        assert_eq!(step.eliminations.len(), 18);
+        assert!(step.eliminations.iter().all(|e| [1, 5, 7].contains(&e.cell.1) && ![0, 4, 8].contains(&e.cell.0)));
+    }
+
+    #[test]
+    fn test_finned_x_wing() {
+        let mut grid = CandidateGrid::from_board(&Board::zeroed());
+        restrict(&mut grid, D4, &[1], &[2, 7]);
+        // r7c9 is the fin, in the box of r7c8 and r9c8
+        restrict(&mut grid, D4, &[6], &[2, 7, 8]);
+        assert!(basic_fish(&grid, 2).is_none());
+        let step = finned_fish(&grid, 2).unwrap();
+        assert_eq!(step.technique, Technique::FinnedXWing);
+        assert_eq!(step.reason, Reason::Fish {
+            digit: D4,
+            base: vec![Unit::Row(1), Unit::Row(6)],
+            cover: vec![Unit::Col(2), Unit::Col(7)],
+            fins: vec![(6, 8)],
+            sashimi: false,
+        });
+        assert_eq!(step.eliminations, vec![Candidate::new((7, 7), D4), Candidate::new((8, 7), D4)]);
+        assert_eq!(
+            step.to_string(),
+            "Finned X-Wing: in rows 2,7, 4 can only go in columns 3,8 or the fin r7c9; r8c8<>4, r9c8<>4."
+        );
+    }
+
+    #[test]
+    fn test_sashimi_x_wing() {
+        let mut grid = CandidateGrid::from_board(&Board::zeroed());
+        restrict(&mut grid, D4, &[1], &[2, 7]);
+        restrict(&mut grid, D4, &[6], &[7, 8]);
+        let step = finned_fish(&grid, 2).unwrap();
+        let Reason::Fish { fins, sashimi, .. } = &step.reason else { panic!("{:?}", step.reason); };
+        assert_eq!(fins, &vec![(6, 8)]);
+        assert!(sashimi);
+        assert_eq!(step.eliminations, vec![Candidate::new((7, 7), D4), Candidate::new((8, 7), D4)]);
+        assert!(step.to_string().contains("or the fin r7c9 (sashimi)"));
+    }
+
+    #[test]
+    fn test_solving_with_fish() {
+        let board: Board = PUZZLE.parse().unwrap();
+        let solution = LogicalSolver::default().solve(&board);
+        assert!(solution.is_solved());
+        assert_eq!(solution.hardest(), Some(Technique::XWing));
+        assert_sound(&board, &solution.steps);
+    }
+}
@@ -0,0 +1,298 @@
+use crate::{
+    game,
+    game::{ CandidateGrid, Candidates, Unit },
+    solver::{ Budget, BudgetExhausted },
+    utilities,
+};
+
+use super::logic::{ Branch, Candidate, Contradiction, Reason, Step, Technique };
+
+/// How deep assumptions may nest in a nishio: at depth 2, a branch that
+/// runs out of singles goes on by ruling out candidates with nishios of
+/// its own. Nested assumptions are only made on strong candidates, as
+/// trying them all costs the square of the candidates.
+const MAX_DEPTH: usize = 2;
+
+fn contradiction(grid: &CandidateGrid) -> Option<Contradiction> {
+    if let Some(cell) = grid.dead_cells().next() {
+        return Some(Contradiction::NoCandidate(cell));
+    }
+    let board = grid.board();
+    for unit in Unit::all() {
+        let mut digits = Candidates::NONE;
+        for (r, c) in unit.cells() {
+            digits = Candidates::from_mask(digits.mask() | grid.candidates(r, c).mask());
+            if let Some(x) = board.at(r, c).digit() { digits.insert(x); }
+        }
+        if let Some(digit) = game::LEGAL_VALUES.into_iter().find(|&x| !digits.contains(x)) {
+            return Some(Contradiction::NoPlace { unit, digit });
+        }
+    }
+    None
+}
+
+// a naked or hidden single
+fn single(grid: &CandidateGrid) -> Option<Candidate> {
+    let naked = (0..81)
+        .map(utilities::coords_from_pos)
+        .find_map(|(r, c)| grid.candidates(r, c).single().map(|x| Candidate::new((r, c), x)));
+    naked.or_else(|| {
+        Unit::all().find_map(|unit| {
+            game::LEGAL_VALUES.into_iter().find_map(|x| match grid.cells_with(unit, x)[..] {
+                [cell] => Some(Candidate::new(cell, x)),
+                _ => None,
+            })
+        })
+    })
+}
+
+fn candidates(grid: &CandidateGrid) -> impl Iterator<Item = Candidate> + '_ {
+    (0..81)
+        .map(utilities::coords_from_pos)
+        .flat_map(|(r, c)| grid.candidates(r, c).iter().map(move |x| Candidate::new((r, c), x)))
+}
+
+// Places `assumption` and singles after it until there are none left or
+// the grid breaks. Above depth 1, nishios of one level less take over
+// whenever the singles run out. Every assumption followed counts as a
+// node of `budget`.
+fn follow(
+    grid: &CandidateGrid,
+    assumption: Candidate,
+    depth: usize,
+    budget: &Budget,
+) -> Result<(Branch, CandidateGrid), BudgetExhausted> {
+    budget.spend_node()?;
+    let mut grid = *grid;
+    let mut branch = Branch { assumption, placements: Vec::new(), refuted: Vec::new(), contradiction: None };
+    grid.place(assumption.cell.0, assumption.cell.1, assumption.digit);
+    loop {
+        branch.contradiction = contradiction(&grid);
+        if branch.contradiction.is_some() { break; }
+
+        if let Some(x) = single(&grid) {
+            grid.place(x.cell.0, x.cell.1, x.digit);
+            branch.placements.push(x);
+        } else if depth > 1 && let Some(refuted) = refute(&grid, depth - 1, budget)? {
+            let x = refuted.assumption;
+            grid.remove_candidate(x.cell.0, x.cell.1, x.digit);
+            branch.refuted.push(x);
+        } else {
+            break;
+        }
+    }
+    Ok((branch, grid))
+}
+
+// whether `x` is one of only two candidates of its cell, or of its digit
+// in a unit, so that ruling it out places the other one
+fn is_strong(grid: &CandidateGrid, x: Candidate) -> bool {
+    grid.candidates(x.cell.0, x.cell.1).len() == 2
+        || Unit::all().any(|unit| unit.contains(x.cell) && grid.cells_with(unit, x.digit).len() == 2)
+}
+
+// the first candidate leading to a contradiction, only strong ones being
+// assumed above depth 1
+fn refute(grid: &CandidateGrid, depth: usize, budget: &Budget) -> Result<Option<Branch>, BudgetExhausted> {
+    for x in candidates(grid).filter(|&x| depth == 1 || is_strong(grid, x)) {
+        let (branch, _) = follow(grid, x, depth, budget)?;
+        if branch.contradiction.is_some() { return Ok(Some(branch)); }
+    }
+    Ok(None)
+}
+
+/// Assumes each candidate in turn, taking it out when singles from it
+/// lead to a contradiction. Only when none does are the assumptions
+/// nested, up to `MAX_DEPTH`.
+pub(super) fn nishio(grid: &CandidateGrid, budget: &Budget) -> Result<Option<Step>, BudgetExhausted> {
+    for depth in 1..=MAX_DEPTH {
+        let Some(branch) = refute(grid, depth, budget)? else { continue; };
+        return Ok(Some(Step {
+            technique: Technique::Nishio,
+            placements: Vec::new(),
+            eliminations: vec![branch.assumption],
+            focus: vec![branch.assumption.cell],
+            reason: Reason::Nishio { branch },
+        }));
+    }
+    Ok(None)
+}
+
+// whether placing `x` takes `y` out
+fn rules_out(x: Candidate, y: Candidate) -> bool {
+    if x.cell == y.cell { return x.digit != y.digit; }
+    x.digit == y.digit && utilities::are_peers(x.cell, y.cell)
+}
+
+// What every branch agrees on, the ones ending in a contradiction aside:
+// a digit they all place, or else a candidate they all take out. The
+// branches are cut short once they get there.
+fn agreement(
+    grid: &CandidateGrid,
+    technique: Technique,
+    unit: Option<Unit>,
+    assumptions: impl Iterator<Item = Candidate>,
+    budget: &Budget,
+) -> Result<Option<Step>, BudgetExhausted> {
+    let followed: Vec<(Branch, CandidateGrid)> = assumptions
+        .map(|x| follow(grid, x, 1, budget))
+        .collect::<Result<_, _>>()?;
+    let live: Vec<&(Branch, CandidateGrid)> = followed.iter().filter(|(branch, _)| branch.contradiction.is_none()).collect();
+    let Some((first, _)) = live.first() else { return Ok(None); };
+
+    let placed = |branch: &Branch, x: Candidate| branch.assumption == x || branch.placements.contains(&x);
+    let placement = [first.assumption].into_iter()
+        .chain(first.placements.iter().copied())
+        .find(|&x| live.iter().all(|(branch, _)| placed(branch, x)));
+    let elimination = || candidates(grid).find(|&x| {
+        live.iter().all(|(_, after)| {
+            !after.candidates(x.cell.0, x.cell.1).contains(x.digit) && after.board().at(x.cell.0, x.cell.1).digit() != Some(x.digit)
+        })
+    });
+
+    let (placements, eliminations, conclusion) = match placement {
+        Some(x) => (vec![x], Vec::new(), x),
+        None => {
+            let Some(x) = elimination() else { return Ok(None); };
+            (Vec::new(), vec![x], x)
+        },
+    };
+    let branches: Vec<Branch> = followed.into_iter()
+        .map(|(mut branch, _)| {
+            if branch.contradiction.is_none() {
+                let reached = |y: Candidate| if placement.is_some() { y == conclusion } else { rules_out(y, conclusion) };
+                if reached(branch.assumption) {
+                    branch.placements.clear();
+                } else if let Some(k) = branch.placements.iter().position(|&y| reached(y)) {
+                    branch.placements.truncate(k + 1);
+                }
+            }
+            branch
+        })
+        .collect();
+
+    let mut focus: Vec<(usize, usize)> = branches.iter().map(|branch| branch.assumption.cell).collect();
+    if !focus.contains(&conclusion.cell) { focus.push(conclusion.cell); }
+    Ok(Some(Step { technique, placements, eliminations, focus, reason: Reason::ForcingChains { unit, branches } }))
+}
+
+/// Assumes each candidate of a cell in turn, fewest candidates first.
+pub(super) fn cell_forcing_chain(grid: &CandidateGrid, budget: &Budget) -> Result<Option<Step>, BudgetExhausted> {
+    for len in 2..=9 {
+        for (r, c) in (0..81).map(utilities::coords_from_pos) {
+            let digits = grid.candidates(r, c);
+            if digits.len() != len { continue; }
+
+            let assumptions = digits.iter().map(|x| Candidate::new((r, c), x));
+            if let Some(step) = agreement(grid, Technique::CellForcingChain, None, assumptions, budget)? {
+                return Ok(Some(step));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Assumes each place of a digit in a unit in turn, fewest places first.
+pub(super) fn unit_forcing_chain(grid: &CandidateGrid, budget: &Budget) -> Result<Option<Step>, BudgetExhausted> {
+    for len in 2..=9 {
+        for unit in Unit::all() {
+            for digit in game::LEGAL_VALUES {
+                let cells = grid.cells_with(unit, digit);
+                if cells.len() != len { continue; }
+
+                let assumptions = cells.into_iter().map(|cell| Candidate::new(cell, digit));
+                if let Some(step) = agreement(grid, Technique::UnitForcingChain, Some(unit), assumptions, budget)? {
+                    return Ok(Some(step));
+                }
+            }
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod forcing_tests {
+    use super::*;
+    use crate::{
+        game::{ Board, Digit::* },
+        smart_solver::logic::{ logic_tests::{ assert_sound, set }, LogicalSolver },
+    };
+
+    const PUZZLE: &str = ".69.4..2.....9...4..78...6.79...5...4.8.....9...4..8.........75.2.6....33.....6..";
+    const TAREK: &str = "12.3....435....1....4........54..2..6...7.........8.9...31..5.......9.7.....6...8";
+    // two solutions, and nothing past a pointing step
+    const TWO_SOLUTIONS: &str = ".....6....59.....82....8....45........3........6..3.54...325..6..................";
+
+    #[test]
+    fn test_nishio() {
+        let mut grid = CandidateGrid::from_board(&Board::zeroed());
+        set(&mut grid, (0, 1), &[D1, D2]);
+        set(&mut grid, (0, 2), &[D1, D2]);
+        let step = nishio(&grid, &Budget::unlimited()).unwrap().unwrap();
+        assert_eq!(step.eliminations, vec![Candidate::new((0, 0), D1)]);
+        assert_eq!(
+            step.to_string(),
+            "Nishio: 1r1c1 => 2r1c2 => r1c3 has no candidate left, so r1c1 can't be 1; r1c1<>1."
+        );
+    }
+
+    #[test]
+    fn test_cell_forcing_chain() {
+        let mut grid = CandidateGrid::from_board(&Board::zeroed());
+        set(&mut grid, (0, 0), &[D1, D2]);
+        set(&mut grid, (0, 1), &[D1, D3]);
+        set(&mut grid, (1, 0), &[D2, D3]);
+        let step = cell_forcing_chain(&grid, &Budget::unlimited()).unwrap().unwrap();
+        assert_eq!(step.eliminations, vec![Candidate::new((0, 2), D1)]);
+        assert_eq!(
+            step.to_string(),
+            "Cell forcing chain: r1c1 holds 12: 1r1c1; 2r1c1 => 3r2c1, 1r1c2; either way r1c3 isn't 1; r1c3<>1."
+        );
+    }
+
+    #[test]
+    fn test_unit_forcing_chain() {
+        let mut grid = CandidateGrid::from_board(&Board::zeroed());
+        for c in [1, 2, 3, 5, 6, 7, 8] {
+            grid.remove_candidate(0, c, D5);
+        }
+        set(&mut grid, (1, 1), &[D5, D7]);
+        set(&mut grid, (1, 4), &[D5, D7]);
+        let step = unit_forcing_chain(&grid, &Budget::unlimited()).unwrap().unwrap();
+        assert_eq!(step.eliminations, vec![Candidate::new((1, 0), D5)]);
+        assert_eq!(
+            step.to_string(),
+            "Unit forcing chain: 5 goes in r1c1,r1c5 in row 1: 5r1c1; 5r1c5 => 7r2c5, 5r2c2; \
+             either way r2c1 isn't 5; r2c1<>5."
+        );
+    }
+
+    #[test]
+    fn test_solving_with_forcing_chains() {
+        let board: Board = PUZZLE.parse().unwrap();
+        let without: Vec<Technique> = Technique::ALL.iter().copied()
+            .filter(|t| !t.assumes_uniqueness() && t.difficulty() <= Technique::AlsChain.difficulty())
+            .collect();
+        assert!(!LogicalSolver::with_techniques(&without).solve(&board).is_solved());
+
+        let solution = LogicalSolver::default().solve(&board);
+        assert!(solution.is_solved());
+        assert_eq!(solution.hardest(), Some(Technique::CellForcingChain));
+        assert_sound(&board, &solution.steps);
+    }
+
+    #[test]
+    fn test_extreme_puzzles_within_bound() {
+        let solver = LogicalSolver::default();
+        let board: Board = TAREK.parse().unwrap();
+        let solution = solver.solve_within(&board, &Budget::unlimited().with_max_nodes(10_000));
+        assert!(solution.is_solved());
+        assert_eq!(solution.hardest(), Some(Technique::Nishio));
+        assert_sound(&board, &solution.steps);
+
+        let board: Board = TWO_SOLUTIONS.parse().unwrap();
+        let solution = solver.solve_within(&board, &Budget::unlimited().with_max_nodes(10_000));
+        assert!(!solution.is_solved());
+        assert!(!solution.is_exhausted());
+    }
+}
@@ -0,0 +1,162 @@
+use std::fmt;
+
+use crate::{
+    game::{ Board, CandidateGrid, Cell },
+    smart_solver,
+    solver,
+    solver::{ Budget, BudgetExhausted },
+};
+
+use super::logic::{ cell_list, LogicalSolver, Step };
+
+/// The answer to "what is the next logical move?" on a board being
+/// played.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Hint {
+    /// The easiest deduction available from the board as it stands.
+    Step(Step),
+    /// The player's entries that don't match the solution, in reading
+    /// order.
+    Mistakes(Vec<(usize, usize)>),
+    /// Every cell is filled in, correctly.
+    Solved,
+    /// The givens have no solution.
+    Unsolvable,
+    /// The givens have more than one solution, so there is nothing to
+    /// reason towards.
+    NotUnique,
+    /// None of the techniques gets any further.
+    Stuck,
+}
+
+impl Hint {
+    /// The cells the hint is about.
+    pub fn focus(&self) -> &[(usize, usize)] {
+        match self {
+            Hint::Step(step) => &step.focus,
+            Hint::Mistakes(cells) => cells,
+            _ => &[],
+        }
+    }
+
+    /// A line short enough for a status bar: for a step, the technique
+    /// and its conclusions only.
+    pub fn summary(&self) -> String {
+        match self {
+            Hint::Step(step) => step.summary(),
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Hint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Hint::Step(step) => write!(f, "{}", step),
+            Hint::Mistakes(cells) => write!(f, "{} don't match the solution.", cell_list(cells)),
+            Hint::Solved => write!(f, "The puzzle is solved."),
+            Hint::Unsolvable => write!(f, "The puzzle has no solution."),
+            Hint::NotUnique => write!(f, "The puzzle has more than one solution."),
+            Hint::Stuck => write!(f, "No technique gets any further."),
+        }
+    }
+}
+
+/// The next logical move on `board`, whose non-givens are the player's
+/// entries. The entries are checked against the unique solution first;
+/// once they all match, the easiest step from them is given, with the
+/// techniques that assume uniqueness included.
+pub fn hint(board: &Board) -> Hint {
+    solver::unbounded(hint_within(board, &Budget::unlimited()))
+}
+
+/// Like `hint`, but gives up once `budget` runs out.
+pub fn hint_within(board: &Board, budget: &Budget) -> Result<Hint, BudgetExhausted> {
+    let givens = board.non_givens_cleared();
+    let mut solutions = smart_solver::solutions(&givens, Some(2)).within(budget);
+    let first = solutions.next();
+    let second = solutions.next();
+    if solutions.is_exhausted() { return Err(BudgetExhausted); }
+    let Some(solution) = first else { return Ok(Hint::Unsolvable); };
+    if second.is_some() { return Ok(Hint::NotUnique); }
+
+    let mistakes: Vec<(usize, usize)> = (0..9)
+        .flat_map(|r| (0..9).map(move |c| (r, c)))
+        .filter(|&(r, c)| match board.at(r, c) {
+            Cell::NonGiven(x) => solution.at(r, c).digit() != Some(x),
+            _ => false,
+        })
+        .collect();
+    if !mistakes.is_empty() { return Ok(Hint::Mistakes(mistakes)); }
+
+    let grid = CandidateGrid::from_board(board);
+    if grid.is_solved() { return Ok(Hint::Solved); }
+    let step = LogicalSolver::default()
+        .assuming_uniqueness()
+        .next_step_within(&grid, budget)?;
+    Ok(step.map_or(Hint::Stuck, Hint::Step))
+}
+
+#[cfg(test)]
+mod hint_tests {
+    use super::*;
+    use crate::{
+        game::Digit::*,
+        smart_solver::logic::{ Candidate, Technique },
+    };
+
+    const PUZZLE: &str = "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79";
+
+    #[test]
+    fn test_hint_on_fresh_puzzle() {
+        let board: Board = PUZZLE.parse().unwrap();
+        let Hint::Step(step) = hint(&board) else { panic!("expected a step") };
+        assert_eq!(step.technique, Technique::NakedSingle);
+        assert_eq!(step.placements, vec![Candidate::new((4, 4), D5)]);
+        assert_eq!(hint(&board).to_string(), "Naked single: r5c5 can only be 5.");
+        assert_eq!(hint(&board).focus(), &[(4, 4)]);
+        assert_eq!(hint(&board).summary(), "Naked single: r5c5=5");
+    }
+
+    #[test]
+    fn test_hint_goes_on_from_entries() {
+        let mut board: Board = PUZZLE.parse().unwrap();
+        board.set_non_given(4, 4, Cell::NonGiven(D5));
+        let Hint::Step(step) = hint(&board) else { panic!("expected a step") };
+        assert!(step.placements.iter().all(|x| x.cell != (4, 4)));
+    }
+
+    #[test]
+    fn test_hint_reports_mistakes() {
+        let mut board: Board = PUZZLE.parse().unwrap();
+        board.set_non_given(4, 4, Cell::NonGiven(D5));
+        board.set_non_given(0, 2, Cell::NonGiven(D1));
+        board.set_non_given(8, 0, Cell::NonGiven(D2));
+        assert_eq!(hint(&board), Hint::Mistakes(vec![(0, 2), (8, 0)]));
+        assert_eq!(hint(&board).to_string(), "r1c3,r9c1 don't match the solution.");
+    }
+
+    #[test]
+    fn test_hint_on_solved_board() {
+        let board = smart_solver::solve(&PUZZLE.parse().unwrap()).unwrap();
+        assert_eq!(hint(&board), Hint::Solved);
+    }
+
+    #[test]
+    fn test_hint_within_budget() {
+        let board: Board = PUZZLE.parse().unwrap();
+        let budget = Budget::unlimited();
+        budget.cancel();
+        assert_eq!(hint_within(&board, &budget), Err(BudgetExhausted));
+        assert_eq!(hint_within(&board, &Budget::unlimited().with_max_nodes(1_000)), Ok(hint(&board)));
+    }
+
+    #[test]
+    fn test_hint_without_unique_solution() {
+        assert_eq!(hint(&Board::zeroed()), Hint::NotUnique);
+        let mut board = Board::zeroed();
+        board.set(0, 0, Cell::Given(D1));
+        board.set(0, 1, Cell::Given(D1));
+        assert_eq!(hint(&board), Hint::Unsolvable);
+    }
+}
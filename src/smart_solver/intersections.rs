@@ -0,0 +1,142 @@
+use crate::{
+    game,
+    game::{ CandidateGrid, Digit, Unit },
+    utilities,
+};
+
+use super::logic::{ Candidate, Reason, Step, Technique };
+
+fn box_of((r, c): (usize, usize)) -> Unit {
+    let (u, l) = utilities::square_limits_from_cell(r, c);
+    Unit::Box(u + l / 3)
+}
+
+// the digit's candidates in `base` all lie in `cover`, so it can go
+// nowhere else in `cover`
+fn locked(grid: &CandidateGrid, technique: Technique, base: Unit, cover: Unit, digit: Digit) -> Option<Step> {
+    let cells = grid.cells_with(base, digit);
+    let eliminations: Vec<Candidate> = grid.cells_with(cover, digit).into_iter()
+        .filter(|&cell| !base.contains(cell))
+        .map(|cell| Candidate::new(cell, digit))
+        .collect();
+    if eliminations.is_empty() { return None; }
+
+    Some(Step {
+        technique,
+        placements: Vec::new(),
+        eliminations,
+        focus: cells.clone(),
+        reason: Reason::LockedCandidates { digit, base, cover, cells },
+    })
+}
+
+/// A box whose candidates for a digit all lie in one row or column.
+pub(super) fn pointing(grid: &CandidateGrid) -> Option<Step> {
+    for s in 0..9 {
+        for digit in game::LEGAL_VALUES {
+            let cells = grid.cells_with(Unit::Box(s), digit);
+            // a single cell is a hidden single
+            let [(r, c), ref rest @ ..] = cells[..] else { continue; };
+            if rest.is_empty() { continue; }
+
+            let cover = if rest.iter().all(|&(i, _)| i == r) {
+                Unit::Row(r)
+            } else if rest.iter().all(|&(_, j)| j == c) {
+                Unit::Col(c)
+            } else {
+                continue;
+            };
+            if let Some(step) = locked(grid, Technique::Pointing, Unit::Box(s), cover, digit) {
+                return Some(step);
+            }
+        }
+    }
+    None
+}
+
+/// A row or column whose candidates for a digit all lie in one box, also
+/// known as box/line reduction.
+pub(super) fn claiming(grid: &CandidateGrid) -> Option<Step> {
+    let lines = (0..9).map(Unit::Row).chain((0..9).map(Unit::Col));
+    for line in lines {
+        for digit in game::LEGAL_VALUES {
+            let cells = grid.cells_with(line, digit);
+            let [first, ref rest @ ..] = cells[..] else { continue; };
+            if rest.is_empty() { continue; }
+
+            let cover = box_of(first);
+            if rest.iter().any(|&cell| box_of(cell) != cover) { continue; }
+            if let Some(step) = locked(grid, Technique::Claiming, line, cover, digit) {
+                return Some(step);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod intersections_tests {
+    use super::*;
+    use crate::{
+        game::{ Board, Digit::* },
+        smart_solver::logic::{ logic_tests::assert_sound, LogicalSolver },
+    };
+
+    const PUZZLE: &str = "....5.3..6......9...39..2.5..9...7..1..39.....321.7.5.9..7...8..84.26....6.8..5..";
+
+    #[test]
+    fn test_pointing() {
+        // the rest of box 1 is filled, so its 1 is in row 1
+        let board: Board = ".........234......567............................................................"
+            .parse().unwrap();
+        let grid = CandidateGrid::from_board(&board);
+        let step = pointing(&grid).unwrap();
+        assert_eq!(step.reason, Reason::LockedCandidates {
+            digit: D1,
+            base: Unit::Box(0),
+            cover: Unit::Row(0),
+            cells: vec![(0, 0), (0, 1), (0, 2)],
+        });
+        assert_eq!(step.eliminations, (3..9).map(|c| Candidate::new((0, c), D1)).collect::<Vec<_>>());
+        assert_eq!(
+            step.to_string(),
+            "Pointing: in box 1, 1 can only go in r1c1,r1c2,r1c3, all in row 1; \
+             r1c4<>1, r1c5<>1, r1c6<>1, r1c7<>1, r1c8<>1, r1c9<>1."
+        );
+    }
+
+    #[test]
+    fn test_claiming() {
+        // the rest of row 1 is filled, so its 1 is in box 1
+        let board: Board = "...234567........................................................................"
+            .parse().unwrap();
+        let grid = CandidateGrid::from_board(&board);
+        assert!(pointing(&grid).is_none());
+        let step = claiming(&grid).unwrap();
+        assert_eq!(step.reason, Reason::LockedCandidates {
+            digit: D1,
+            base: Unit::Row(0),
+            cover: Unit::Box(0),
+            cells: vec![(0, 0), (0, 1), (0, 2)],
+        });
+        assert_eq!(step.eliminations.len(), 6);
+        assert!(step.eliminations.iter().all(|e| e.digit == D1 && e.cell.0 != 0 && e.cell.1 < 3));
+    }
+
+    #[test]
+    fn test_solving_with_intersections() {
+        let board: Board = PUZZLE.parse().unwrap();
+        let singles = LogicalSolver::with_techniques(&[Technique::NakedSingle, Technique::HiddenSingle]);
+        assert!(!singles.solve(&board).is_solved());
+
+        let solution = LogicalSolver::with_techniques(&[
+            Technique::NakedSingle,
+            Technique::HiddenSingle,
+            Technique::Pointing,
+            Technique::Claiming,
+        ]).solve(&board);
+        assert!(solution.is_solved());
+        assert!(matches!(solution.hardest(), Some(Technique::Pointing | Technique::Claiming)));
+        assert_sound(&board, &solution.steps);
+    }
+}
@@ -0,0 +1,918 @@
+use std::fmt;
+
+use crate::{
+    game::{ Board, CandidateGrid, Candidates, Digit, Unit },
+    solver,
+    solver::{ Budget, BudgetExhausted },
+    utilities,
+};
+
+use super::{ als, chains, coloring, fish, forcing, intersections, single_digit, singles, subsets, uniqueness, wings };
+
+/// A digit in a cell, either placed there or as one of its candidates.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Candidate {
+    pub cell: (usize, usize),
+    pub digit: Digit,
+}
+
+impl Candidate {
+    pub fn new(cell: (usize, usize), digit: Digit) -> Self {
+        Candidate { cell, digit }
+    }
+}
+
+/// The usual candidate notation, digit first: `4r5c3`.
+impl fmt::Display for Candidate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.digit, utilities::cell_name(self.cell.0, self.cell.1))
+    }
+}
+
+/// The only two places left for a digit in `unit`, so that one of them
+/// holds it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct StrongLink {
+    pub unit: Unit,
+    pub cells: [(usize, usize); 2],
+}
+
+impl StrongLink {
+    pub fn reversed(self) -> Self {
+        StrongLink { unit: self.unit, cells: [self.cells[1], self.cells[0]] }
+    }
+}
+
+/// `r2c5=r2c1`
+impl fmt::Display for StrongLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [(r, c), (i, j)] = self.cells;
+        write!(f, "{}={}", utilities::cell_name(r, c), utilities::cell_name(i, j))
+    }
+}
+
+/// Candidates joined by links alternating strong and weak, the first
+/// one strong: if the first candidate is false, the last one is true.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Chain(pub Vec<Candidate>);
+
+/// Eureka notation, the candidates of one cell grouped together:
+/// `(1)r1c1=(1)r1c5-(1=2)r4c5-(2=3)r4c1`
+impl fmt::Display for Chain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // links alternate from a strong one
+        let link = |i: usize| if i.is_multiple_of(2) { "=" } else { "-" };
+        for (i, node) in self.0.iter().enumerate() {
+            let next = self.0.get(i + 1);
+            if i == 0 || self.0[i - 1].cell != node.cell { write!(f, "(")?; }
+            write!(f, "{}", node.digit)?;
+            match next {
+                Some(next) if next.cell == node.cell => write!(f, "{}", link(i))?,
+                _ => {
+                    write!(f, "){}", utilities::cell_name(node.cell.0, node.cell.1))?;
+                    if next.is_some() { write!(f, "{}", link(i))?; }
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Cells of one unit holding one more digit between them than they are
+/// many: take any digit away and the others are locked in the cells.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AlmostLockedSet {
+    pub unit: Unit,
+    pub cells: Vec<(usize, usize)>,
+    pub digits: Candidates,
+}
+
+/// `(125)r2c1,r2c5`
+impl fmt::Display for AlmostLockedSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}){}", self.digits, cell_list(&self.cells))
+    }
+}
+
+/// What makes a grid impossible to complete.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Contradiction {
+    /// An empty cell is left without candidates.
+    NoCandidate((usize, usize)),
+    /// A digit missing from `unit` has no place left in it.
+    NoPlace { unit: Unit, digit: Digit },
+}
+
+impl fmt::Display for Contradiction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Contradiction::NoCandidate((r, c)) => write!(f, "{} has no candidate left", utilities::cell_name(*r, *c)),
+            Contradiction::NoPlace { unit, digit } => write!(f, "{} has no place left in {}", digit, unit),
+        }
+    }
+}
+
+/// What follows from assuming a candidate true: the singles it leads to,
+/// in order, the candidates ruled out on the way by nested assumptions,
+/// and the contradiction it ends in, if any.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Branch {
+    pub assumption: Candidate,
+    pub placements: Vec<Candidate>,
+    pub refuted: Vec<Candidate>,
+    pub contradiction: Option<Contradiction>,
+}
+
+/// `7r1c1 => 5r1c4, 3r2c2 => r5c5 has no candidate left`
+impl fmt::Display for Branch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.assumption)?;
+        if !self.placements.is_empty() {
+            let placements: Vec<String> = self.placements.iter().map(|x| x.to_string()).collect();
+            write!(f, " => {}", placements.join(", "))?;
+        }
+        if !self.refuted.is_empty() {
+            write!(f, " (ruling out {} on the way)", elimination_list(&self.refuted))?;
+        }
+        if let Some(contradiction) = self.contradiction {
+            write!(f, " => {}", contradiction)?;
+        }
+        Ok(())
+    }
+}
+
+/// How a coloring step makes its eliminations.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ColoringRule {
+    /// Two cells of the first color see each other, so that color is false.
+    Wrap,
+    /// The eliminated cells see both colors of the cluster.
+    Trap,
+    /// A cell of the first color of one cluster sees one of the first
+    /// color of the other, so one of the second colors is true.
+    Linked,
+}
+
+/// Logical techniques, from the easiest.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Technique {
+    NakedSingle,
+    HiddenSingle,
+    Pointing,
+    Claiming,
+    NakedPair,
+    HiddenPair,
+    NakedTriple,
+    HiddenTriple,
+    NakedQuad,
+    HiddenQuad,
+    UniqueRectangle1,
+    UniqueRectangle2,
+    UniqueRectangle4,
+    BugPlusOne,
+    UniqueRectangle3,
+    XYWing,
+    XYZWing,
+    WWing,
+    XWing,
+    Skyscraper,
+    TwoStringKite,
+    FinnedXWing,
+    TurbotFish,
+    EmptyRectangle,
+    Swordfish,
+    SimpleColoring,
+    FinnedSwordfish,
+    MultiColoring,
+    Jellyfish,
+    FinnedJellyfish,
+    XChain,
+    XYChain,
+    ContinuousNiceLoop,
+    DiscontinuousNiceLoop,
+    Aic,
+    AlsXz,
+    AlsXyWing,
+    AlsChain,
+    CellForcingChain,
+    UnitForcingChain,
+    Nishio,
+}
+
+impl Technique {
+    pub const ALL: &[Technique] = &[
+        Technique::NakedSingle,
+        Technique::HiddenSingle,
+        Technique::Pointing,
+        Technique::Claiming,
+        Technique::NakedPair,
+        Technique::HiddenPair,
+        Technique::NakedTriple,
+        Technique::HiddenTriple,
+        Technique::NakedQuad,
+        Technique::HiddenQuad,
+        Technique::UniqueRectangle1,
+        Technique::UniqueRectangle2,
+        Technique::UniqueRectangle4,
+        Technique::BugPlusOne,
+        Technique::UniqueRectangle3,
+        Technique::XYWing,
+        Technique::XYZWing,
+        Technique::WWing,
+        Technique::XWing,
+        Technique::Skyscraper,
+        Technique::TwoStringKite,
+        Technique::FinnedXWing,
+        Technique::TurbotFish,
+        Technique::EmptyRectangle,
+        Technique::Swordfish,
+        Technique::SimpleColoring,
+        Technique::FinnedSwordfish,
+        Technique::MultiColoring,
+        Technique::Jellyfish,
+        Technique::FinnedJellyfish,
+        Technique::XChain,
+        Technique::XYChain,
+        Technique::ContinuousNiceLoop,
+        Technique::DiscontinuousNiceLoop,
+        Technique::Aic,
+        Technique::AlsXz,
+        Technique::AlsXyWing,
+        Technique::AlsChain,
+        Technique::CellForcingChain,
+        Technique::UnitForcingChain,
+        Technique::Nishio,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Technique::NakedSingle => "Naked single",
+            Technique::HiddenSingle => "Hidden single",
+            Technique::Pointing => "Pointing",
+            Technique::Claiming => "Claiming",
+            Technique::NakedPair => "Naked pair",
+            Technique::HiddenPair => "Hidden pair",
+            Technique::NakedTriple => "Naked triple",
+            Technique::HiddenTriple => "Hidden triple",
+            Technique::NakedQuad => "Naked quad",
+            Technique::HiddenQuad => "Hidden quad",
+            Technique::UniqueRectangle1 => "Unique rectangle type 1",
+            Technique::UniqueRectangle2 => "Unique rectangle type 2",
+            Technique::UniqueRectangle3 => "Unique rectangle type 3",
+            Technique::UniqueRectangle4 => "Unique rectangle type 4",
+            Technique::BugPlusOne => "BUG+1",
+            Technique::XYWing => "XY-Wing",
+            Technique::XYZWing => "XYZ-Wing",
+            Technique::WWing => "W-Wing",
+            Technique::XWing => "X-Wing",
+            Technique::Skyscraper => "Skyscraper",
+            Technique::TwoStringKite => "2-String Kite",
+            Technique::FinnedXWing => "Finned X-Wing",
+            Technique::TurbotFish => "Turbot fish",
+            Technique::EmptyRectangle => "Empty rectangle",
+            Technique::Swordfish => "Swordfish",
+            Technique::SimpleColoring => "Simple coloring",
+            Technique::FinnedSwordfish => "Finned Swordfish",
+            Technique::MultiColoring => "Multi-coloring",
+            Technique::Jellyfish => "Jellyfish",
+            Technique::FinnedJellyfish => "Finned Jellyfish",
+            Technique::XChain => "X-Chain",
+            Technique::XYChain => "XY-Chain",
+            Technique::ContinuousNiceLoop => "Continuous nice loop",
+            Technique::DiscontinuousNiceLoop => "Discontinuous nice loop",
+            Technique::Aic => "AIC",
+            Technique::AlsXz => "ALS-XZ",
+            Technique::AlsXyWing => "ALS-XY-Wing",
+            Technique::AlsChain => "ALS chain",
+            Technique::CellForcingChain => "Cell forcing chain",
+            Technique::UnitForcingChain => "Unit forcing chain",
+            Technique::Nishio => "Nishio",
+        }
+    }
+
+    /// Rough difficulty score, for rating a puzzle by its hardest step.
+    pub fn difficulty(self) -> u32 {
+        match self {
+            Technique::NakedSingle => 10,
+            Technique::HiddenSingle => 12,
+            Technique::Pointing => 17,
+            Technique::Claiming => 18,
+            Technique::NakedPair => 20,
+            Technique::HiddenPair => 24,
+            Technique::NakedTriple => 30,
+            Technique::HiddenTriple => 34,
+            Technique::NakedQuad => 40,
+            Technique::HiddenQuad => 44,
+            Technique::UniqueRectangle1 => 45,
+            Technique::UniqueRectangle2 => 45,
+            Technique::UniqueRectangle3 => 46,
+            Technique::UniqueRectangle4 => 45,
+            Technique::BugPlusOne => 45,
+            Technique::XYWing => 46,
+            Technique::XYZWing => 48,
+            Technique::WWing => 49,
+            Technique::XWing => 50,
+            Technique::Skyscraper => 51,
+            Technique::TwoStringKite => 51,
+            Technique::FinnedXWing => 52,
+            Technique::TurbotFish => 53,
+            Technique::EmptyRectangle => 53,
+            Technique::Swordfish => 54,
+            Technique::SimpleColoring => 55,
+            Technique::FinnedSwordfish => 56,
+            Technique::MultiColoring => 57,
+            Technique::Jellyfish => 58,
+            Technique::FinnedJellyfish => 60,
+            Technique::XChain => 62,
+            Technique::XYChain => 64,
+            Technique::ContinuousNiceLoop => 66,
+            Technique::DiscontinuousNiceLoop => 66,
+            Technique::Aic => 68,
+            Technique::AlsXz => 70,
+            Technique::AlsXyWing => 72,
+            Technique::AlsChain => 74,
+            Technique::CellForcingChain => 80,
+            Technique::UnitForcingChain => 82,
+            Technique::Nishio => 90,
+        }
+    }
+
+    /// Tells whether the technique relies on the puzzle having a single
+    /// solution, which makes it unsound for puzzles not known to be unique.
+    pub fn assumes_uniqueness(self) -> bool {
+        matches!(
+            self,
+            Technique::UniqueRectangle1
+                | Technique::UniqueRectangle2
+                | Technique::UniqueRectangle3
+                | Technique::UniqueRectangle4
+                | Technique::BugPlusOne
+        )
+    }
+}
+
+impl fmt::Display for Technique {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Why a step holds, in terms of the cells and candidates involved.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Reason {
+    /// Every other digit is gone from the cell. `blockers` are filled
+    /// peers holding some of those digits; the digits none of them
+    /// holds were `eliminated` by earlier steps.
+    NakedSingle { blockers: Vec<(usize, usize)>, eliminated: Vec<Candidate> },
+    /// The digit has no other place in `unit`. `blockers` are filled
+    /// cells holding the digit that see the rest of the unit; the other
+    /// cells, which none of them sees, had it `eliminated` by earlier
+    /// steps.
+    HiddenSingle { unit: Unit, blockers: Vec<(usize, usize)>, eliminated: Vec<Candidate> },
+    /// The digit's `cells` in `base` all lie in `cover` too, the two
+    /// units being a box and a line, so it goes nowhere else in `cover`.
+    LockedCandidates { digit: Digit, base: Unit, cover: Unit, cells: Vec<(usize, usize)> },
+    /// As many `cells` of `unit` as `digits` hold nothing but those
+    /// digits, so the digits go nowhere else in the unit.
+    NakedSubset { unit: Unit, cells: Vec<(usize, usize)>, digits: Candidates },
+    /// As many `digits` as `cells` only go in those cells of `unit`, so
+    /// the cells hold no other digits.
+    HiddenSubset { unit: Unit, cells: Vec<(usize, usize)>, digits: Candidates },
+    /// The digit's candidates in the `base` lines all lie in as many
+    /// `cover` lines, save for `fins` sharing a box. Sashimi when a base
+    /// line has one candidate left besides the fins.
+    Fish {
+        digit: Digit,
+        base: Vec<Unit>,
+        cover: Vec<Unit>,
+        fins: Vec<(usize, usize)>,
+        sashimi: bool,
+    },
+    /// One of the `pincers`, which both see the `pivot`, is `digit`; for
+    /// an XYZ-Wing, the pivot may be it as well.
+    Wing { pivot: (usize, usize), pincers: [(usize, usize); 2], digit: Digit },
+    /// The `pincers` hold the same two `digits` and `link_digit` only
+    /// goes in the two `pivot` cells of `link`, each seeing a pincer.
+    WWing {
+        pincers: [(usize, usize); 2],
+        digits: Candidates,
+        link: Unit,
+        pivot: [(usize, usize); 2],
+        link_digit: Digit,
+    },
+    /// Two strong links a1=a2 and b1=b2 on `digit`, with a2 seeing b1,
+    /// so one of a1 and b2 holds it.
+    StrongLinks { digit: Digit, links: [StrongLink; 2] },
+    /// The candidates of `unit`, a box, all lie in the row and column of
+    /// `cross`; the near end of `link` is in one of them and its far end
+    /// sees where the other one leaves the box.
+    EmptyRectangle { digit: Digit, unit: Unit, cross: [Unit; 2], link: StrongLink },
+    /// Clusters of conjugate pairs on `digit`, each split in its two
+    /// colors, every pair having one cell of each.
+    Coloring { digit: Digit, clusters: Vec<[Vec<(usize, usize)>; 2]>, rule: ColoringRule },
+    /// The `corners` of a rectangle over two boxes all hold both `digits`,
+    /// which could be swapped if the `roof` corners held nothing else.
+    /// `extra` is what the roof holds besides them for types 1 and 2, the
+    /// naked subset's digits in `unit` with the `subset` cells for type 3,
+    /// and the eliminated digit of the pair for type 4.
+    UniqueRectangle {
+        corners: [(usize, usize); 4],
+        digits: Candidates,
+        roof: Vec<(usize, usize)>,
+        extra: Candidates,
+        unit: Option<Unit>,
+        subset: Vec<(usize, usize)>,
+    },
+    /// Every unsolved cell but `cell` has two candidates, and the placed
+    /// digit is the one left three times in `unit`.
+    BugPlusOne { cell: (usize, usize), unit: Unit },
+    /// An alternating inference chain. For a continuous nice loop the
+    /// chain ends where it started, through a weak link, and every weak
+    /// link of the loop is strong as well.
+    Chain { chain: Chain },
+    /// Almost locked sets, each joined to the next by one of the `links`,
+    /// a digit both hold that can't be in both; two links in a row are
+    /// different digits. Then `digit` is in the first set or the last.
+    AlmostLockedSets { sets: Vec<AlmostLockedSet>, links: Vec<Digit>, digit: Digit },
+    /// Every candidate of a cell, or every place of a digit in `unit`,
+    /// leads to the step's conclusion or to a contradiction.
+    ForcingChains { unit: Option<Unit>, branches: Vec<Branch> },
+    /// Assuming the eliminated candidate leads to a contradiction.
+    Nishio { branch: Branch },
+}
+
+/// One deduction: digits placed and candidates eliminated, with the
+/// cells the step is about and the reason it holds.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Step {
+    pub technique: Technique,
+    pub placements: Vec<Candidate>,
+    pub eliminations: Vec<Candidate>,
+    pub focus: Vec<(usize, usize)>,
+    pub reason: Reason,
+}
+
+impl Step {
+    pub fn apply(&self, grid: &mut CandidateGrid) {
+        for placement in self.placements.iter() {
+            let (r, c) = placement.cell;
+            grid.place(r, c, placement.digit);
+        }
+        for elimination in self.eliminations.iter() {
+            let (r, c) = elimination.cell;
+            grid.remove_candidate(r, c, elimination.digit);
+        }
+    }
+
+    /// The technique and what it concludes, e.g. "Nishio: r1c1<>1", for
+    /// where the full sentence doesn't fit.
+    pub fn summary(&self) -> String {
+        let placements = self.placements.iter()
+            .map(|x| format!("{}={}", utilities::cell_name(x.cell.0, x.cell.1), x.digit));
+        let eliminations = (!self.eliminations.is_empty()).then(|| elimination_list(&self.eliminations));
+        let conclusions: Vec<String> = placements.chain(eliminations).collect();
+        format!("{}: {}", self.technique, conclusions.join(", "))
+    }
+}
+
+pub(super) fn cell_list(cells: &[(usize, usize)]) -> String {
+    cells.iter()
+        .map(|&(r, c)| utilities::cell_name(r, c))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+// candidates that are ruled out, as `r1c3<>1, r2c2<>4`
+fn elimination_list(candidates: &[Candidate]) -> String {
+    candidates.iter()
+        .map(|x| format!("{}<>{}", utilities::cell_name(x.cell.0, x.cell.1), x.digit))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// candidates for `digit` in the cells that see all of `cells`
+pub(super) fn seen_by_all(grid: &CandidateGrid, cells: &[(usize, usize)], digit: Digit) -> Vec<Candidate> {
+    (0..81)
+        .map(utilities::coords_from_pos)
+        .filter(|&(r, c)| grid.candidates(r, c).contains(digit))
+        .filter(|&cell| cells.iter().all(|&other| utilities::are_peers(cell, other)))
+        .map(|cell| Candidate::new(cell, digit))
+        .collect()
+}
+
+// lines of one kind, e.g. "rows 2,7"
+fn unit_list(units: &[Unit]) -> String {
+    let kind = match units.first() {
+        Some(Unit::Row(_)) => "rows",
+        Some(Unit::Col(_)) => "columns",
+        _ => "boxes",
+    };
+    let numbers: Vec<String> = units.iter()
+        .map(|&unit| match unit { Unit::Row(i) | Unit::Col(i) | Unit::Box(i) => (i + 1).to_string() })
+        .collect();
+    format!("{} {}", kind, numbers.join(","))
+}
+
+// every way of picking `k` of the items, in order
+pub(super) fn combinations<T: Copy>(items: &[T], k: usize) -> Vec<Vec<T>> {
+    if k == 0 { return vec![Vec::new()]; }
+    if items.len() < k { return Vec::new(); }
+
+    let mut with_first = combinations(&items[1..], k - 1);
+    for combination in with_first.iter_mut() {
+        combination.insert(0, items[0]);
+    }
+    with_first.extend(combinations(&items[1..], k));
+    with_first
+}
+
+/// A sentence for players, e.g. "Hidden single: in box 4, 7 can only
+/// go in r5c3."
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.technique)?;
+        match &self.reason {
+            Reason::NakedSingle { eliminated, .. } => {
+                let placement = self.placements[0];
+                write!(f, "{} can only be {}", cell_list(&[placement.cell]), placement.digit)?;
+                if !eliminated.is_empty() {
+                    write!(f, " with {} ruled out before", elimination_list(eliminated))?;
+                }
+            },
+            Reason::HiddenSingle { unit, eliminated, .. } => {
+                let placement = self.placements[0];
+                write!(f, "in {}, {} can only go in {}", unit, placement.digit, cell_list(&[placement.cell]))?;
+                if !eliminated.is_empty() {
+                    write!(f, " with {} ruled out before", elimination_list(eliminated))?;
+                }
+            },
+            Reason::LockedCandidates { digit, base, cover, cells } => {
+                write!(f, "in {}, {} can only go in {}, all in {}", base, digit, cell_list(cells), cover)?;
+            },
+            Reason::NakedSubset { unit, cells, digits } => {
+                write!(f, "in {}, {} only hold {}", unit, cell_list(cells), digits)?;
+            },
+            Reason::HiddenSubset { unit, cells, digits } => {
+                write!(f, "in {}, {} only go in {}", unit, digits, cell_list(cells))?;
+            },
+            Reason::Fish { digit, base, cover, fins, sashimi } => {
+                write!(f, "in {}, {} can only go in {}", unit_list(base), digit, unit_list(cover))?;
+                match fins.len() {
+                    0 => {},
+                    1 => write!(f, " or the fin {}", cell_list(fins))?,
+                    _ => write!(f, " or the fins {}", cell_list(fins))?,
+                }
+                if *sashimi { write!(f, " (sashimi)")?; }
+            },
+            Reason::Wing { pivot, pincers, digit } => {
+                let which = if self.technique == Technique::XYZWing { "one of the three" } else { "one of which" };
+                write!(f, "pivot {} with pincers {}, {} is {}", cell_list(&[*pivot]), cell_list(pincers), which, digit)?;
+            },
+            Reason::WWing { pincers, digits, link, pivot, link_digit } => {
+                let other = Candidates::from_mask(digits.mask() & !link_digit.bit());
+                write!(
+                    f,
+                    "{} and {} are both {}, and {} in {} only goes in {}, so one of them is {}",
+                    cell_list(&pincers[..1]), cell_list(&pincers[1..]), digits, link_digit, link, cell_list(pivot), other
+                )?;
+            },
+            Reason::StrongLinks { digit, links: [first, second] } => {
+                write!(
+                    f,
+                    "{} in {} and {}, {}-{}, so {} or {} is {}",
+                    digit, first.unit, second.unit, first, second,
+                    cell_list(&first.cells[..1]), cell_list(&second.cells[1..]), digit
+                )?;
+            },
+            Reason::EmptyRectangle { digit, unit, cross: [row, col], link } => {
+                write!(f, "in {}, {} only goes in {} or {}, and {} in {}", unit, digit, row, col, link, link.unit)?;
+            },
+            Reason::Coloring { digit, clusters, rule } => {
+                let colors: Vec<String> = clusters.iter()
+                    .map(|[first, second]| format!("{} | {}", cell_list(first), cell_list(second)))
+                    .collect();
+                write!(f, "{} colored {}, ", digit, colors.join(" and "))?;
+                match rule {
+                    ColoringRule::Wrap => write!(f, "two cells of the first color see each other")?,
+                    ColoringRule::Trap => write!(f, "one of the two colors is true")?,
+                    ColoringRule::Linked => write!(f, "the first colors see each other, so one of the second ones is true")?,
+                }
+            },
+            Reason::UniqueRectangle { corners, digits, roof, extra, unit, subset } => {
+                write!(f, "{} would hold {} twice over, ", cell_list(corners), digits)?;
+                match (self.technique, unit) {
+                    (Technique::UniqueRectangle2, _) => write!(f, "so one of {} is {}", cell_list(roof), extra)?,
+                    (Technique::UniqueRectangle3, Some(unit)) => write!(
+                        f,
+                        "so {} and {} only hold {} in {}",
+                        cell_list(roof), cell_list(subset), extra, unit
+                    )?,
+                    (Technique::UniqueRectangle4, Some(unit)) => write!(
+                        f,
+                        "and {} only goes in {} in {}, so neither is {}",
+                        Candidates::from_mask(digits.mask() & !extra.mask()), cell_list(roof), unit, extra
+                    )?,
+                    _ => write!(f, "so {} holds something else", cell_list(roof))?,
+                }
+            },
+            Reason::BugPlusOne { cell, unit } => {
+                let digit = self.placements[0].digit;
+                write!(
+                    f,
+                    "every unsolved cell but {} has two candidates, and {} is left three times in {}, so {} is {}",
+                    cell_list(&[*cell]), digit, unit, cell_list(&[*cell]), digit
+                )?;
+            },
+            Reason::Chain { chain } => {
+                let (first, last) = (chain.0[0], chain.0[chain.0.len() - 1]);
+                match self.technique {
+                    Technique::ContinuousNiceLoop => {
+                        write!(f, "{}, a loop, so every weak link in it is strong as well", chain)?;
+                    },
+                    _ if first == last => write!(f, "{}, so {} is {}", chain, cell_list(&[first.cell]), first.digit)?,
+                    _ if first.cell == last.cell => write!(
+                        f,
+                        "{}, so {} is {} or {}",
+                        chain, cell_list(&[first.cell]), first.digit, last.digit
+                    )?,
+                    _ => write!(f, "{}, so {} or {} is true", chain, first, last)?,
+                }
+            },
+            Reason::AlmostLockedSets { sets, links, digit } => {
+                write!(f, "{}", sets[0])?;
+                for (set, link) in sets[1..].iter().zip(links) {
+                    write!(f, " -{}- {}", link, set)?;
+                }
+                write!(f, ", so {} is in the first set or the last", digit)?;
+            },
+            Reason::ForcingChains { unit, branches } => {
+                let assumed: Vec<Candidate> = branches.iter().map(|branch| branch.assumption).collect();
+                match unit {
+                    Some(unit) => write!(
+                        f,
+                        "{} goes in {} in {}: ",
+                        assumed[0].digit, cell_list(&assumed.iter().map(|x| x.cell).collect::<Vec<_>>()), unit
+                    )?,
+                    None => write!(
+                        f,
+                        "{} holds {}: ",
+                        cell_list(&[assumed[0].cell]), assumed.iter().map(|x| x.digit).collect::<Candidates>()
+                    )?,
+                }
+                let branches: Vec<String> = branches.iter().map(|branch| branch.to_string()).collect();
+                write!(f, "{}; either way ", branches.join("; "))?;
+                match (self.placements.first(), self.eliminations.first()) {
+                    (Some(x), _) => write!(f, "{} is {}", cell_list(&[x.cell]), x.digit)?,
+                    (None, Some(x)) => write!(f, "{} isn't {}", cell_list(&[x.cell]), x.digit)?,
+                    (None, None) => {},
+                }
+            },
+            Reason::Nishio { branch } => {
+                let x = branch.assumption;
+                write!(f, "{}, so {} can't be {}", branch, cell_list(&[x.cell]), x.digit)?;
+            },
+        }
+        if !self.eliminations.is_empty() {
+            write!(f, "; {}", elimination_list(&self.eliminations))?;
+        }
+        write!(f, ".")
+    }
+}
+
+// Only the forcing techniques, which follow assumptions, spend nodes of
+// the budget as they go.
+fn find_step(technique: Technique, grid: &CandidateGrid, budget: &Budget) -> Result<Option<Step>, BudgetExhausted> {
+    let step = match technique {
+        Technique::NakedSingle => singles::naked_single(grid),
+        Technique::HiddenSingle => singles::hidden_single(grid),
+        Technique::Pointing => intersections::pointing(grid),
+        Technique::Claiming => intersections::claiming(grid),
+        Technique::NakedPair => subsets::naked_subset(grid, 2),
+        Technique::HiddenPair => subsets::hidden_subset(grid, 2),
+        Technique::NakedTriple => subsets::naked_subset(grid, 3),
+        Technique::HiddenTriple => subsets::hidden_subset(grid, 3),
+        Technique::NakedQuad => subsets::naked_subset(grid, 4),
+        Technique::HiddenQuad => subsets::hidden_subset(grid, 4),
+        Technique::UniqueRectangle1 => uniqueness::unique_rectangle_1(grid),
+        Technique::UniqueRectangle2 => uniqueness::unique_rectangle_2(grid),
+        Technique::UniqueRectangle3 => uniqueness::unique_rectangle_3(grid),
+        Technique::UniqueRectangle4 => uniqueness::unique_rectangle_4(grid),
+        Technique::BugPlusOne => uniqueness::bug_plus_one(grid),
+        Technique::XYWing => wings::xy_wing(grid),
+        Technique::XYZWing => wings::xyz_wing(grid),
+        Technique::WWing => wings::w_wing(grid),
+        Technique::XWing => fish::basic_fish(grid, 2),
+        Technique::Skyscraper => single_digit::skyscraper(grid),
+        Technique::TwoStringKite => single_digit::two_string_kite(grid),
+        Technique::FinnedXWing => fish::finned_fish(grid, 2),
+        Technique::TurbotFish => single_digit::turbot_fish(grid),
+        Technique::EmptyRectangle => single_digit::empty_rectangle(grid),
+        Technique::Swordfish => fish::basic_fish(grid, 3),
+        Technique::SimpleColoring => coloring::simple_coloring(grid),
+        Technique::FinnedSwordfish => fish::finned_fish(grid, 3),
+        Technique::MultiColoring => coloring::multi_coloring(grid),
+        Technique::Jellyfish => fish::basic_fish(grid, 4),
+        Technique::FinnedJellyfish => fish::finned_fish(grid, 4),
+        Technique::XChain => chains::x_chain(grid),
+        Technique::XYChain => chains::xy_chain(grid),
+        Technique::ContinuousNiceLoop => chains::continuous_nice_loop(grid),
+        Technique::DiscontinuousNiceLoop => chains::discontinuous_nice_loop(grid),
+        Technique::Aic => chains::aic(grid),
+        Technique::AlsXz => als::als_xz(grid),
+        Technique::AlsXyWing => als::als_xy_wing(grid),
+        Technique::AlsChain => als::als_chain(grid),
+        Technique::CellForcingChain => forcing::cell_forcing_chain(grid, budget)?,
+        Technique::UnitForcingChain => forcing::unit_forcing_chain(grid, budget)?,
+        Technique::Nishio => forcing::nishio(grid, budget)?,
+    };
+    Ok(step)
+}
+
+/// Solves like a person would: one step at a time, always with the
+/// easiest technique that applies, and never guessing.
+#[derive(Debug, Clone)]
+pub struct LogicalSolver {
+    /// Techniques tried, in this order
+    pub techniques: Vec<Technique>,
+}
+
+/// Every technique but those assuming a unique solution.
+impl Default for LogicalSolver {
+    fn default() -> Self {
+        LogicalSolver {
+            techniques: Technique::ALL.iter().copied().filter(|t| !t.assumes_uniqueness()).collect(),
+        }
+    }
+}
+
+impl LogicalSolver {
+    pub fn with_techniques(techniques: &[Technique]) -> Self {
+        LogicalSolver { techniques: techniques.to_vec() }
+    }
+
+    /// Adds the techniques relying on a unique solution, for puzzles
+    /// known to have one.
+    pub fn assuming_uniqueness(mut self) -> Self {
+        self.techniques = Technique::ALL.iter().copied()
+            .filter(|t| t.assumes_uniqueness() || self.techniques.contains(t))
+            .collect();
+        self
+    }
+
+    pub fn next_step(&self, grid: &CandidateGrid) -> Option<Step> {
+        solver::unbounded(self.next_step_within(grid, &Budget::unlimited()))
+    }
+
+    /// Like `next_step`, but gives up once `budget` runs out. Every
+    /// technique tried counts as a node, and so does every assumption
+    /// the forcing techniques follow.
+    pub fn next_step_within(&self, grid: &CandidateGrid, budget: &Budget) -> Result<Option<Step>, BudgetExhausted> {
+        for &technique in self.techniques.iter() {
+            budget.spend_node()?;
+            if let Some(step) = find_step(technique, grid, budget)? { return Ok(Some(step)); }
+        }
+        Ok(None)
+    }
+
+    // Applies steps until the grid is solved, a cell runs out of
+    // candidates, no technique applies anymore or the budget runs out.
+    fn solve_grid(&self, mut grid: CandidateGrid, budget: &Budget) -> LogicalSolution {
+        let mut steps = Vec::new();
+        let mut exhausted = false;
+        while !grid.is_solved() && grid.dead_cells().next().is_none() {
+            let step = match self.next_step_within(&grid, budget) {
+                Ok(Some(step)) => step,
+                Ok(None) => break,
+                Err(BudgetExhausted) => {
+                    exhausted = true;
+                    break;
+                },
+            };
+            step.apply(&mut grid);
+            steps.push(step);
+        }
+        LogicalSolution { steps, grid, exhausted }
+    }
+
+    pub fn solve(&self, board: &Board) -> LogicalSolution {
+        self.solve_within(board, &Budget::unlimited())
+    }
+
+    /// Like `solve`, but stops where it got to once `budget` runs out.
+    pub fn solve_within(&self, board: &Board, budget: &Budget) -> LogicalSolution {
+        self.solve_grid(CandidateGrid::from_board(board), budget)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LogicalSolution {
+    pub steps: Vec<Step>,
+    /// Where the solver ended up
+    pub grid: CandidateGrid,
+    exhausted: bool,
+}
+
+impl LogicalSolution {
+    /// Tells whether the steps solved the puzzle, i.e. without guessing.
+    pub fn is_solved(&self) -> bool {
+        self.grid.is_solved()
+    }
+
+    /// Tells whether the solver stopped because its budget ran out,
+    /// rather than because it was done or stuck.
+    pub fn is_exhausted(&self) -> bool {
+        self.exhausted
+    }
+
+    /// The most difficult technique the solution needed.
+    pub fn hardest(&self) -> Option<Technique> {
+        self.steps.iter()
+            .map(|step| step.technique)
+            .max_by_key(|technique| technique.difficulty())
+    }
+}
+
+#[cfg(test)]
+pub(super) mod logic_tests {
+    use super::*;
+    use crate::smart_solver;
+
+    // every placement agrees with the solution and no elimination
+    // removes a digit of the solution
+    pub(in crate::smart_solver) fn assert_sound(board: &Board, steps: &[Step]) {
+        let solution = smart_solver::solve(board).unwrap();
+        for step in steps {
+            for p in step.placements.iter() {
+                assert_eq!(solution.at(p.cell.0, p.cell.1).digit(), Some(p.digit), "{}", step);
+            }
+            for e in step.eliminations.iter() {
+                assert_ne!(solution.at(e.cell.0, e.cell.1).digit(), Some(e.digit), "{}", step);
+            }
+        }
+    }
+
+    // gives (r, c) exactly `digits` as candidates
+    pub(in crate::smart_solver) fn set(grid: &mut CandidateGrid, (r, c): (usize, usize), digits: &[Digit]) {
+        grid.set_candidates(r, c, digits.iter().copied().collect());
+    }
+
+    // keeps `digit` only in `keep` among the cells of `unit`
+    pub(in crate::smart_solver) fn restrict(grid: &mut CandidateGrid, digit: Digit, unit: Unit, keep: &[(usize, usize)]) {
+        for (r, c) in unit.cells() {
+            if !keep.contains(&(r, c)) {
+                grid.remove_candidate(r, c, digit);
+            }
+        }
+    }
+
+    #[test]
+    fn test_solving_with_singles() {
+        let board: Board = "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79"
+            .parse().unwrap();
+        let solution = LogicalSolver::default().solve(&board);
+        assert!(solution.is_solved());
+        assert_eq!(solution.steps.len(), 51);
+        assert!(solution.hardest().is_some());
+        assert_sound(&board, &solution.steps);
+    }
+
+    #[test]
+    fn test_stuck_without_guessing() {
+        let board: Board = "1....7.9..3..2...8..96..5....53..9...1..8...26....4...3......1..4......7..7...3.."
+            .parse().unwrap();
+        let solver = LogicalSolver::with_techniques(&[Technique::NakedSingle, Technique::HiddenSingle]);
+        let solution = solver.solve(&board);
+        assert!(!solution.is_solved());
+        assert!(solver.next_step(&solution.grid).is_none());
+        assert_sound(&board, &solution.steps);
+    }
+
+    #[test]
+    fn test_solving_within_budget() {
+        let board: Board = "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79"
+            .parse().unwrap();
+        let solver = LogicalSolver::default();
+        let budget = Budget::unlimited().with_max_nodes(10);
+        let solution = solver.solve_within(&board, &budget);
+        assert!(solution.is_exhausted());
+        assert!(!solution.is_solved());
+        assert!(!solution.steps.is_empty());
+        assert_sound(&board, &solution.steps);
+
+        let budget = Budget::unlimited();
+        budget.cancel();
+        assert!(matches!(solver.next_step_within(&CandidateGrid::from_board(&board), &budget), Err(BudgetExhausted)));
+        assert!(!solver.solve(&board).is_exhausted());
+    }
+
+    #[test]
+    fn test_step_display() {
+        let board: Board = "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79"
+            .parse().unwrap();
+        let grid = CandidateGrid::from_board(&board);
+        let step = LogicalSolver::with_techniques(&[Technique::NakedSingle]).next_step(&grid).unwrap();
+        assert_eq!(step.to_string(), "Naked single: r5c5 can only be 5.");
+        assert_eq!(step.summary(), "Naked single: r5c5=5");
+        assert_eq!(Candidate::new((4, 2), Digit::D7).to_string(), "7r5c3");
+    }
+}
@@ -0,0 +1,495 @@
+use crate::{
+    game,
+    game::{ Board, Cell, Digit },
+    solver,
+    solver::{ Budget, BudgetExhausted, Solver, SolverStats },
+    utilities,
+};
+
+mod als;
+mod chains;
+mod coloring;
+mod fish;
+mod forcing;
+mod hint;
+mod intersections;
+pub mod logic;
+mod single_digit;
+mod singles;
+mod subsets;
+mod uniqueness;
+mod wings;
+
+pub use hint::{ hint, hint_within, Hint };
+pub use logic::{
+    AlmostLockedSet, Branch, Candidate, Chain, ColoringRule, Contradiction, LogicalSolution, LogicalSolver, Reason,
+    Step, StrongLink, Technique,
+};
+
+const ALL_CANDIDATES: u16 = game::ALL_DIGITS;
+
+// every row, column and square as a list of cell positions
+const UNITS: [[usize; 9]; 27] = {
+    let mut units = [[0; 9]; 27];
+    let mut i = 0;
+    while i < 9 {
+        let (u, l) = (i - i % 3, (i % 3) * 3);
+        let mut j = 0;
+        while j < 9 {
+            units[i][j] = i * 9 + j;
+            units[9 + i][j] = j * 9 + i;
+            units[18 + i][j] = (u + j / 3) * 9 + l + j % 3;
+            j += 1;
+        }
+        i += 1;
+    }
+    units
+};
+
+// the 20 cells sharing a row, column or square with a given cell
+const PEERS: [[usize; 20]; 81] = {
+    let mut peers = [[0; 20]; 81];
+    let mut pos = 0;
+    while pos < 81 {
+        let (r, c) = (pos / 9, pos % 9);
+        let mut n = 0;
+        let mut other = 0;
+        while other < 81 {
+            let (i, j) = (other / 9, other % 9);
+            let same_square = i / 3 == r / 3 && j / 3 == c / 3;
+            if other != pos && (i == r || j == c || same_square) {
+                peers[pos][n] = other;
+                n += 1;
+            }
+            other += 1;
+        }
+        pos += 1;
+    }
+    peers
+};
+
+/// Candidates of every cell together with the digits placed so far.
+#[derive(Copy, Clone)]
+pub(crate) struct SearchState {
+    values: [Option<Digit>; 81],
+    pub(crate) candidates: [u16; 81],
+}
+
+impl SearchState {
+    /// Returns `None` if the filled cells of the board already clash.
+    pub(crate) fn from_board(board: &Board) -> Option<Self> {
+        let mut state = SearchState {
+            values: [None; 81],
+            candidates: [ALL_CANDIDATES; 81],
+        };
+        for pos in 0..81 {
+            let (r, c) = utilities::coords_from_pos(pos);
+            match board.at(r, c) {
+                Cell::Given(x) | Cell::NonGiven(x) => {
+                    if state.candidates[pos] & x.bit() == 0
+                        || !state.assign(pos, x)
+                    {
+                        return None;
+                    }
+                },
+                Cell::Empty => {},
+            }
+        }
+        Some(state)
+    }
+
+    /// Places `digit` and removes it from the candidates of all peers.
+    /// Returns `false` if some empty peer is left without candidates.
+    pub(crate) fn assign(&mut self, pos: usize, digit: Digit) -> bool {
+        let bit = digit.bit();
+        self.values[pos] = Some(digit);
+        self.candidates[pos] = 0;
+        for &peer in PEERS[pos].iter() {
+            if self.values[peer].is_some() { continue; }
+            self.candidates[peer] &= !bit;
+            if self.candidates[peer] == 0 { return false; }
+        }
+        true
+    }
+
+    /// Fills naked and hidden singles until none are left.
+    /// Returns `false` on contradiction.
+    pub(crate) fn propagate(&mut self) -> bool {
+        loop {
+            let mut progress = false;
+
+            // naked singles
+            for pos in 0..81 {
+                if self.values[pos].is_some() { continue; }
+                let cands = self.candidates[pos];
+                if cands == 0 { return false; }
+                if cands.count_ones() == 1 {
+                    let digit = game::LEGAL_VALUES[cands.trailing_zeros() as usize];
+                    if !self.assign(pos, digit) { return false; }
+                    progress = true;
+                }
+            }
+
+            // hidden singles
+            for unit in UNITS.iter() {
+                let mut placed = 0;
+                let mut seen_once = 0;
+                let mut seen_more = 0;
+                for &pos in unit.iter() {
+                    match self.values[pos] {
+                        Some(x) => placed |= x.bit(),
+                        None => {
+                            let cands = self.candidates[pos];
+                            seen_more |= seen_once & cands;
+                            seen_once |= cands;
+                        },
+                    }
+                }
+                // some digit has nowhere to go in this unit
+                if placed | seen_once != ALL_CANDIDATES { return false; }
+
+                let mut singles = seen_once & !seen_more & !placed;
+                while singles != 0 {
+                    let digit = game::LEGAL_VALUES[singles.trailing_zeros() as usize];
+                    singles &= singles - 1;
+                    let bit = digit.bit();
+                    let Some(&pos) = unit.iter()
+                        .find(|&&p| self.candidates[p] & bit != 0)
+                    else {
+                        // already consumed by a peer placed in this pass
+                        return false;
+                    };
+                    if !self.assign(pos, digit) { return false; }
+                    progress = true;
+                }
+            }
+
+            if !progress { return true; }
+        }
+    }
+
+    /// Empty cell with the fewest candidates, or `None` if the grid is full.
+    pub(crate) fn most_constrained(&self) -> Option<usize> {
+        let mut best = None;
+        let mut best_count = u32::MAX;
+        for pos in 0..81 {
+            if self.values[pos].is_some() { continue; }
+            let count = self.candidates[pos].count_ones();
+            if count < best_count {
+                best = Some(pos);
+                best_count = count;
+                if count == 2 { break; }
+            }
+        }
+        best
+    }
+
+    /// The board with every cell filled in from `values`, keeping the
+    /// cells that were already filled in `board`.
+    pub(crate) fn filled_board(&self, board: &Board) -> Board {
+        let mut solution = *board;
+        for pos in 0..81 {
+            let (r, c) = utilities::coords_from_pos(pos);
+            if let (Cell::Empty, Some(x)) = (board.at(r, c), self.values[pos]) {
+                solution.set(r, c, Cell::NonGiven(x));
+            }
+        }
+        solution
+    }
+}
+
+/// Depth-first search calling `on_solution` for every solution found,
+/// until it returns `false`. Returns `false` if stopped that way.
+pub(crate) fn search<F: FnMut(&SearchState) -> bool>(
+    mut state: SearchState,
+    depth: usize,
+    budget: &Budget,
+    stats: &mut SolverStats,
+    on_solution: &mut F,
+) -> Result<bool, BudgetExhausted> {
+    stats.visit(depth);
+    budget.spend_node()?;
+    if !state.propagate() {
+        stats.backtracks += 1;
+        return Ok(true);
+    }
+
+    let Some(pos) = state.most_constrained() else {
+        return Ok(on_solution(&state));
+    };
+
+    let mut cands = state.candidates[pos];
+    while cands != 0 {
+        let digit = game::LEGAL_VALUES[cands.trailing_zeros() as usize];
+        cands &= cands - 1;
+
+        stats.candidates_tried += 1;
+        let mut next = state;
+        if !next.assign(pos, digit) {
+            stats.backtracks += 1;
+            continue;
+        }
+        if !search(next, depth + 1, budget, stats, on_solution)? { return Ok(false); }
+    }
+    Ok(true)
+}
+
+fn count_up_to(board: &Board, limit: u64, budget: &Budget, stats: &mut SolverStats) -> Result<u64, BudgetExhausted> {
+    let mut count = 0;
+    if limit == 0 { return Ok(count); }
+    let Some(state) = SearchState::from_board(board) else { return Ok(count); };
+
+    search(state, 0, budget, stats, &mut |_| {
+        count += 1;
+        count < limit
+    })?;
+    Ok(count)
+}
+
+/// Counts the solutions of `board`, treating non-givens as fixed.
+/// With `test_unique` set the search stops as soon as a second
+/// solution is found, so the result is capped at 2.
+pub fn count_solutions(board: &Board, test_unique: bool) -> u64 {
+    let limit = if test_unique { 2 } else { u64::MAX };
+    solver::unbounded(count_up_to(board, limit, &Budget::unlimited(), &mut SolverStats::default()))
+}
+
+pub fn is_uniquely_solvable(board: &Board) -> bool {
+    count_solutions(board, true) == 1
+}
+
+pub fn solve(board: &Board) -> Option<Board> {
+    solutions(board, None).next()
+}
+
+// a branching point of the search: the state before the branch cell was
+// filled in and the digits not yet tried there
+struct Frame {
+    state: SearchState,
+    pos: usize,
+    untried: u16,
+}
+
+/// Lazily enumerates the solutions of a board, depth first. The search
+/// only runs as far as needed to produce the next solution.
+pub struct Solutions {
+    board: Board,
+    pending: Option<SearchState>,
+    stack: Vec<Frame>,
+    remaining: Option<usize>,
+    stats: SolverStats,
+    budget: Budget,
+    exhausted: bool,
+}
+
+impl Solutions {
+    /// Makes the iterator end early once `budget` runs out.
+    pub fn within(mut self, budget: &Budget) -> Self {
+        self.budget = budget.clone();
+        self
+    }
+
+    /// Work done so far, without the elapsed time.
+    pub fn stats(&self) -> &SolverStats {
+        &self.stats
+    }
+
+    /// Tells whether the iterator ended because its budget ran out,
+    /// rather than because there are no more solutions.
+    pub fn is_exhausted(&self) -> bool {
+        self.exhausted
+    }
+}
+
+impl Iterator for Solutions {
+    type Item = Board;
+
+    fn next(&mut self) -> Option<Board> {
+        if self.remaining == Some(0) { return None; }
+
+        loop {
+            if let Some(mut state) = self.pending.take() {
+                self.stats.visit(self.stack.len());
+                if self.budget.spend_node().is_err() {
+                    self.exhausted = true;
+                    self.stack.clear();
+                    return None;
+                }
+                if !state.propagate() {
+                    self.stats.backtracks += 1;
+                    continue;
+                }
+                match state.most_constrained() {
+                    Some(pos) => {
+                        let untried = state.candidates[pos];
+                        self.stack.push(Frame { state, pos, untried });
+                    },
+                    None => {
+                        if let Some(remaining) = self.remaining.as_mut() {
+                            *remaining -= 1;
+                        }
+                        return Some(state.filled_board(&self.board));
+                    },
+                }
+                continue;
+            }
+
+            let frame = self.stack.last_mut()?;
+            if frame.untried == 0 {
+                self.stack.pop();
+                continue;
+            }
+            let digit = game::LEGAL_VALUES[frame.untried.trailing_zeros() as usize];
+            frame.untried &= frame.untried - 1;
+
+            self.stats.candidates_tried += 1;
+            let mut next = frame.state;
+            if next.assign(frame.pos, digit) {
+                self.pending = Some(next);
+            } else {
+                self.stats.backtracks += 1;
+            }
+        }
+    }
+}
+
+/// Iterator over the solutions of `board`, yielding at most `limit` of
+/// them if one is given. Empty cells are filled in as non-givens.
+pub fn solutions(board: &Board, limit: Option<usize>) -> Solutions {
+    Solutions {
+        board: *board,
+        pending: SearchState::from_board(board),
+        stack: Vec::new(),
+        remaining: limit,
+        stats: SolverStats::default(),
+        budget: Budget::unlimited(),
+        exhausted: false,
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SmartSolver;
+
+impl Solver for SmartSolver {
+    fn search_solutions(
+        &self,
+        board: &Board,
+        limit: usize,
+        budget: &Budget,
+        stats: &mut SolverStats,
+    ) -> Result<Vec<Board>, BudgetExhausted> {
+        let mut iter = solutions(board, Some(limit)).within(budget);
+        let found = iter.by_ref().collect();
+        stats.merge(iter.stats());
+        if iter.is_exhausted() { return Err(BudgetExhausted); }
+        Ok(found)
+    }
+
+    fn search_count(
+        &self,
+        board: &Board,
+        limit: u64,
+        budget: &Budget,
+        stats: &mut SolverStats,
+    ) -> Result<u64, BudgetExhausted> {
+        count_up_to(board, limit, budget, stats)
+    }
+}
+
+#[cfg(test)]
+mod smart_solver_tests {
+    use super::*;
+
+    #[test]
+    fn test_unique_puzzle() {
+        let board: Board = "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79"
+            .parse().unwrap();
+        assert_eq!(count_solutions(&board, true), 1);
+        assert_eq!(count_solutions(&board, false), 1);
+        assert!(is_uniquely_solvable(&board));
+    }
+
+    #[test]
+    fn test_hard_17_clue_puzzle() {
+        let board: Board = "000000010400000000020000000000050407008000300001090000300400200050100000000806000"
+            .parse().unwrap();
+        assert!(is_uniquely_solvable(&board));
+    }
+
+    #[test]
+    fn test_multiple_solutions() {
+        // 8 and 2 in rows 0 and 4, columns 0 and 2 can be swapped
+        let board: Board = ".7.415369691783542435926781763258194.5.194637149637258387542916516879423924361875"
+            .parse().unwrap();
+        assert_eq!(count_solutions(&board, false), 2);
+        assert_eq!(count_solutions(&board, true), 2);
+        assert!(!is_uniquely_solvable(&board));
+    }
+
+    #[test]
+    fn test_exact_count() {
+        let board: Board = "872415369691783542435926781763258194258194637149637258..........................."
+            .parse().unwrap();
+        assert_eq!(count_solutions(&board, true), 2);
+        assert_eq!(count_solutions(&board, false), 96);
+    }
+
+    #[test]
+    fn test_no_solution() {
+        let board: Board = "11..............................................................................."
+            .parse().unwrap();
+        assert_eq!(count_solutions(&board, false), 0);
+
+        // (0, 8) can't hold anything
+        let board: Board = "12345678.........9..............................................................."
+            .parse().unwrap();
+        assert_eq!(count_solutions(&board, false), 0);
+    }
+
+    #[test]
+    fn test_enumerating_solutions() {
+        let board: Board = "872415369691783542435926781763258194258194637149637258..........................."
+            .parse().unwrap();
+
+        let all: Vec<Board> = solutions(&board, None).collect();
+        assert_eq!(all.len(), 96);
+        assert!(all.iter().all(crate::evaluator::evaluate));
+        for (i, a) in all.iter().enumerate() {
+            for b in all.iter().skip(i + 1) {
+                assert!((0..81).any(|pos| a.at(pos / 9, pos % 9) != b.at(pos / 9, pos % 9)));
+            }
+        }
+
+        assert_eq!(solutions(&board, Some(10)).count(), 10);
+        assert_eq!(solutions(&board, Some(0)).count(), 0);
+
+        // resuming picks up where the last solution left off
+        let mut iter = solutions(&board, None);
+        let first_three: Vec<Board> = iter.by_ref().take(3).collect();
+        assert_eq!(iter.count(), 93);
+        for (a, b) in first_three.iter().zip(all.iter()) {
+            assert!((0..81).all(|pos| a.at(pos / 9, pos % 9) == b.at(pos / 9, pos % 9)));
+        }
+    }
+
+    #[test]
+    fn test_enumeration_within_budget() {
+        let board: Board = "872415369691783542435926781763258194258194637149637258..........................."
+            .parse().unwrap();
+
+        let mut iter = solutions(&board, None).within(&Budget::unlimited().with_max_nodes(30));
+        assert!(iter.by_ref().count() < 96);
+        assert!(iter.is_exhausted());
+
+        let mut iter = solutions(&board, None).within(&Budget::unlimited().with_max_nodes(100_000));
+        assert_eq!(iter.by_ref().count(), 96);
+        assert!(!iter.is_exhausted());
+    }
+
+    #[test]
+    fn test_enumerating_no_solution() {
+        let board: Board = "11..............................................................................."
+            .parse().unwrap();
+        assert_eq!(solutions(&board, None).count(), 0);
+    }
+}
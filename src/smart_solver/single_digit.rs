@@ -0,0 +1,227 @@
+use crate::{
+    game,
+    game::{ CandidateGrid, Digit, Unit },
+    utilities,
+};
+
+use super::logic::{ seen_by_all, Candidate, Reason, Step, StrongLink, Technique };
+
+// every unit where `digit` has exactly two places left
+pub(super) fn strong_links(grid: &CandidateGrid, digit: Digit) -> Vec<StrongLink> {
+    Unit::all()
+        .filter_map(|unit| match grid.cells_with(unit, digit)[..] {
+            [a, b] => Some(StrongLink { unit, cells: [a, b] }),
+            _ => None,
+        })
+        .collect()
+}
+
+fn same_box((r, c): (usize, usize), (i, j): (usize, usize)) -> bool {
+    utilities::square_limits_from_cell(r, c) == utilities::square_limits_from_cell(i, j)
+}
+
+// what two strong links joined by a weak one, a1=a2-b1=b2, are called
+fn classify(first: &StrongLink, second: &StrongLink) -> Technique {
+    let [_, a2] = first.cells;
+    let [b1, _] = second.cells;
+    match (first.unit, second.unit) {
+        (Unit::Row(_), Unit::Row(_)) if a2.1 == b1.1 => Technique::Skyscraper,
+        (Unit::Col(_), Unit::Col(_)) if a2.0 == b1.0 => Technique::Skyscraper,
+        (Unit::Row(_), Unit::Col(_)) | (Unit::Col(_), Unit::Row(_)) if same_box(a2, b1) => Technique::TwoStringKite,
+        _ => Technique::TurbotFish,
+    }
+}
+
+/// Two strong links on a digit, a1=a2 and b1=b2, with a2 seeing b1: a2
+/// and b1 can't both be the digit, so a1 or b2 is. Skyscrapers and
+/// 2-String Kites are the turbot fish with two lines.
+fn linked_pair(grid: &CandidateGrid, technique: Technique) -> Option<Step> {
+    for digit in game::LEGAL_VALUES {
+        let links = strong_links(grid, digit);
+        for (i, first) in links.iter().enumerate() {
+            for second in links[i + 1..].iter() {
+                if first.cells.iter().any(|cell| second.cells.contains(cell)) { continue; }
+
+                for first in [*first, first.reversed()] {
+                    for second in [*second, second.reversed()] {
+                        let ([a1, a2], [b1, b2]) = (first.cells, second.cells);
+                        if !utilities::are_peers(a2, b1) || classify(&first, &second) != technique { continue; }
+
+                        let eliminations = seen_by_all(grid, &[a1, b2], digit);
+                        if eliminations.is_empty() { continue; }
+
+                        return Some(Step {
+                            technique,
+                            placements: Vec::new(),
+                            eliminations,
+                            focus: vec![a1, a2, b1, b2],
+                            reason: Reason::StrongLinks { digit, links: [first, second] },
+                        });
+                    }
+                }
+            }
+        }
+    }
+    None
+}
+
+pub(super) fn skyscraper(grid: &CandidateGrid) -> Option<Step> {
+    linked_pair(grid, Technique::Skyscraper)
+}
+
+pub(super) fn two_string_kite(grid: &CandidateGrid) -> Option<Step> {
+    linked_pair(grid, Technique::TwoStringKite)
+}
+
+pub(super) fn turbot_fish(grid: &CandidateGrid) -> Option<Step> {
+    linked_pair(grid, Technique::TurbotFish)
+}
+
+/// A box whose candidates for a digit all lie in one row and one column
+/// of it, with a strong link in a line crossing that row (or column)
+/// outside the box. If the far end of the link took the digit, the box
+/// would have no place left for it where that end's line meets the
+/// box's column (or row).
+pub(super) fn empty_rectangle(grid: &CandidateGrid) -> Option<Step> {
+    for digit in game::LEGAL_VALUES {
+        let links = strong_links(grid, digit);
+        for s in 0..9 {
+            let cells = grid.cells_with(Unit::Box(s), digit);
+            if cells.len() < 2 { continue; }
+            let (u, l) = utilities::square_limits_from_square(s);
+
+            for (row, col) in (u..u + 3).flat_map(|r| (l..l + 3).map(move |c| (r, c))) {
+                if cells.iter().any(|&(r, c)| r != row && c != col) { continue; }
+                // all in one line is just pointing
+                if cells.iter().all(|&(r, _)| r == row) || cells.iter().all(|&(_, c)| c == col) { continue; }
+
+                for link in links.iter() {
+                    for [near, far] in [link.cells, link.reversed().cells] {
+                        let target = match link.unit {
+                            Unit::Col(_) if near.0 == row && !(l..l + 3).contains(&near.1)
+                                && !(u..u + 3).contains(&far.0) => (far.0, col),
+                            Unit::Row(_) if near.1 == col && !(u..u + 3).contains(&near.0)
+                                && !(l..l + 3).contains(&far.1) => (row, far.1),
+                            _ => continue,
+                        };
+                        if !grid.candidates(target.0, target.1).contains(digit) { continue; }
+
+                        let mut focus = cells.clone();
+                        focus.extend(link.cells);
+                        return Some(Step {
+                            technique: Technique::EmptyRectangle,
+                            placements: Vec::new(),
+                            eliminations: vec![Candidate::new(target, digit)],
+                            focus,
+                            reason: Reason::EmptyRectangle {
+                                digit,
+                                unit: Unit::Box(s),
+                                cross: [Unit::Row(row), Unit::Col(col)],
+                                link: StrongLink { unit: link.unit, cells: [near, far] },
+                            },
+                        });
+                    }
+                }
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod single_digit_tests {
+    use super::*;
+    use crate::{
+        game::{ Board, Digit::* },
+        smart_solver::logic::{ logic_tests::{ assert_sound, restrict }, LogicalSolver },
+    };
+
+    const PUZZLE: &str = "...394....7.......2.......4....879.6.1...6..2..89...5....8....9682...4...497..5..";
+
+    #[test]
+    fn test_skyscraper() {
+        let mut grid = CandidateGrid::from_board(&Board::zeroed());
+        restrict(&mut grid, D4, Unit::Row(1), &[(1, 0), (1, 5)]);
+        restrict(&mut grid, D4, Unit::Row(6), &[(6, 0), (6, 4)]);
+        assert!(two_string_kite(&grid).is_none());
+        let step = skyscraper(&grid).unwrap();
+        assert_eq!(step.reason, Reason::StrongLinks {
+            digit: D4,
+            links: [
+                StrongLink { unit: Unit::Row(1), cells: [(1, 5), (1, 0)] },
+                StrongLink { unit: Unit::Row(6), cells: [(6, 0), (6, 4)] },
+            ],
+        });
+        assert_eq!(step.eliminations, [(0, 4), (2, 4), (7, 5), (8, 5)].map(|cell| Candidate::new(cell, D4)));
+        assert!(step.to_string().starts_with(
+            "Skyscraper: 4 in row 2 and row 7, r2c6=r2c1-r7c1=r7c5, so r2c6 or r7c5 is 4; r1c5<>4, "
+        ));
+    }
+
+    #[test]
+    fn test_two_string_kite() {
+        let mut grid = CandidateGrid::from_board(&Board::zeroed());
+        restrict(&mut grid, D5, Unit::Row(0), &[(0, 1), (0, 6)]);
+        restrict(&mut grid, D5, Unit::Col(0), &[(2, 0), (7, 0)]);
+        assert!(skyscraper(&grid).is_none());
+        let step = two_string_kite(&grid).unwrap();
+        assert_eq!(step.reason, Reason::StrongLinks {
+            digit: D5,
+            links: [
+                StrongLink { unit: Unit::Row(0), cells: [(0, 6), (0, 1)] },
+                StrongLink { unit: Unit::Col(0), cells: [(2, 0), (7, 0)] },
+            ],
+        });
+        assert_eq!(step.eliminations, vec![Candidate::new((7, 6), D5)]);
+    }
+
+    #[test]
+    fn test_turbot_fish() {
+        let mut grid = CandidateGrid::from_board(&Board::zeroed());
+        restrict(&mut grid, D6, Unit::Row(4), &[(4, 1), (4, 7)]);
+        restrict(&mut grid, D6, Unit::Box(0), &[(1, 1), (2, 2)]);
+        assert!(skyscraper(&grid).is_none());
+        assert!(two_string_kite(&grid).is_none());
+        let step = turbot_fish(&grid).unwrap();
+        assert_eq!(step.reason, Reason::StrongLinks {
+            digit: D6,
+            links: [
+                StrongLink { unit: Unit::Row(4), cells: [(4, 7), (4, 1)] },
+                StrongLink { unit: Unit::Box(0), cells: [(1, 1), (2, 2)] },
+            ],
+        });
+        assert_eq!(step.eliminations, vec![Candidate::new((2, 7), D6)]);
+    }
+
+    #[test]
+    fn test_empty_rectangle() {
+        let mut grid = CandidateGrid::from_board(&Board::zeroed());
+        for cell in [(0, 0), (0, 2), (2, 0), (2, 2)] {
+            grid.remove_candidate(cell.0, cell.1, D7);
+        }
+        assert!(empty_rectangle(&grid).is_none());
+
+        restrict(&mut grid, D7, Unit::Col(5), &[(1, 5), (6, 5)]);
+        let step = empty_rectangle(&grid).unwrap();
+        assert_eq!(step.reason, Reason::EmptyRectangle {
+            digit: D7,
+            unit: Unit::Box(0),
+            cross: [Unit::Row(1), Unit::Col(1)],
+            link: StrongLink { unit: Unit::Col(5), cells: [(1, 5), (6, 5)] },
+        });
+        assert_eq!(step.eliminations, vec![Candidate::new((6, 1), D7)]);
+        assert_eq!(
+            step.to_string(),
+            "Empty rectangle: in box 1, 7 only goes in row 2 or column 2, and r2c6=r7c6 in column 6; r7c2<>7."
+        );
+    }
+
+    #[test]
+    fn test_solving_with_single_digit_patterns() {
+        let board: Board = PUZZLE.parse().unwrap();
+        let solution = LogicalSolver::default().solve(&board);
+        assert!(solution.is_solved());
+        assert_eq!(solution.hardest(), Some(Technique::Skyscraper));
+        assert_sound(&board, &solution.steps);
+    }
+}
@@ -0,0 +1,139 @@
+use crate::{
+    game,
+    game::{ Board, CandidateGrid, Cell, Digit, Unit },
+    utilities,
+};
+
+use super::logic::{ Candidate, Reason, Step, Technique };
+
+// a filled peer of (r, c) holding `digit`, if there is one
+fn filled_peer_with(board: &Board, (r, c): (usize, usize), digit: Digit) -> Option<(usize, usize)> {
+    utilities::peers(r, c).find(|&(i, j)| board.at(i, j).digit() == Some(digit))
+}
+
+pub(super) fn naked_single(grid: &CandidateGrid) -> Option<Step> {
+    let board = grid.board();
+    for (r, c) in (0..81).map(utilities::coords_from_pos) {
+        let Some(digit) = grid.candidates(r, c).single() else { continue; };
+
+        let mut blockers = Vec::new();
+        let mut eliminated = Vec::new();
+        for other in game::LEGAL_VALUES.into_iter().filter(|&x| x != digit) {
+            match filled_peer_with(board, (r, c), other) {
+                Some(peer) => if !blockers.contains(&peer) { blockers.push(peer); },
+                None => eliminated.push(Candidate::new((r, c), other)),
+            }
+        }
+        return Some(Step {
+            technique: Technique::NakedSingle,
+            placements: vec![Candidate::new((r, c), digit)],
+            eliminations: Vec::new(),
+            focus: vec![(r, c)],
+            reason: Reason::NakedSingle { blockers, eliminated },
+        });
+    }
+    None
+}
+
+pub(super) fn hidden_single(grid: &CandidateGrid) -> Option<Step> {
+    let board = grid.board();
+    // boxes first, that's where people look for them
+    let units = (0..9).map(Unit::Box)
+        .chain((0..9).map(Unit::Row))
+        .chain((0..9).map(Unit::Col));
+    for unit in units {
+        for digit in game::LEGAL_VALUES {
+            let cells = grid.cells_with(unit, digit);
+            let [target] = cells[..] else { continue; };
+
+            let mut blockers = Vec::new();
+            let mut eliminated = Vec::new();
+            for cell in unit.cells() {
+                if cell == target || board.at(cell.0, cell.1) != Cell::Empty { continue; }
+                match filled_peer_with(board, cell, digit) {
+                    Some(peer) => if !blockers.contains(&peer) { blockers.push(peer); },
+                    None => eliminated.push(Candidate::new(cell, digit)),
+                }
+            }
+            return Some(Step {
+                technique: Technique::HiddenSingle,
+                placements: vec![Candidate::new(target, digit)],
+                eliminations: Vec::new(),
+                focus: vec![target],
+                reason: Reason::HiddenSingle { unit, blockers, eliminated },
+            });
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod singles_tests {
+    use super::*;
+    use crate::game::Digit::*;
+
+    #[test]
+    fn test_naked_single() {
+        let board: Board = "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79"
+            .parse().unwrap();
+        let grid = CandidateGrid::from_board(&board);
+        let step = naked_single(&grid).unwrap();
+        assert_eq!(step.placements, vec![Candidate::new((4, 4), D5)]);
+        let Reason::NakedSingle { blockers, eliminated } = &step.reason else { panic!("{:?}", step.reason); };
+        assert!(eliminated.is_empty());
+        // each of the other 8 digits is somewhere among the peers
+        let seen: Vec<Digit> = blockers.iter().filter_map(|&(r, c)| board.at(r, c).digit()).collect();
+        assert_eq!(seen.len(), 8);
+        assert!(!seen.contains(&D5));
+    }
+
+    #[test]
+    fn test_hidden_single() {
+        // every other cell of box 1 sees a 1
+        let board: Board = ".............1...........1...........1...........................1..............."
+            .parse().unwrap();
+        let grid = CandidateGrid::from_board(&board);
+        assert!(naked_single(&grid).is_none());
+        let step = hidden_single(&grid).unwrap();
+        assert_eq!(step.placements, vec![Candidate::new((0, 0), D1)]);
+        assert_eq!(step.reason, Reason::HiddenSingle {
+            unit: Unit::Box(0),
+            blockers: vec![(4, 1), (7, 2), (1, 4), (2, 7)],
+            eliminated: Vec::new(),
+        });
+        assert_eq!(step.to_string(), "Hidden single: in box 1, 1 can only go in r1c1.");
+    }
+
+    #[test]
+    fn test_singles_after_eliminations() {
+        let board: Board = "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79"
+            .parse().unwrap();
+        let mut grid = CandidateGrid::from_board(&board);
+        // r1c3 holds 124 by its peers
+        grid.remove_candidate(0, 2, D1);
+        grid.remove_candidate(0, 2, D2);
+        let step = naked_single(&grid).unwrap();
+        assert_eq!(step.placements, vec![Candidate::new((0, 2), D4)]);
+        let Reason::NakedSingle { eliminated, .. } = &step.reason else { panic!("{:?}", step.reason); };
+        assert_eq!(eliminated, &vec![Candidate::new((0, 2), D1), Candidate::new((0, 2), D2)]);
+        assert_eq!(step.to_string(), "Naked single: r1c3 can only be 4 with r1c3<>1, r1c3<>2 ruled out before.");
+
+        // the 1s of r2c5 and r3c8 block two rows of box 1, the rest of
+        // the first row lost its 1 to earlier steps
+        let board: Board = ".............1...........1......................................................."
+            .parse().unwrap();
+        let mut grid = CandidateGrid::from_board(&board);
+        grid.remove_candidate(0, 1, D1);
+        grid.remove_candidate(0, 2, D1);
+        let step = hidden_single(&grid).unwrap();
+        assert_eq!(step.reason, Reason::HiddenSingle {
+            unit: Unit::Box(0),
+            blockers: vec![(1, 4), (2, 7)],
+            eliminated: vec![Candidate::new((0, 1), D1), Candidate::new((0, 2), D1)],
+        });
+        assert_eq!(
+            step.to_string(),
+            "Hidden single: in box 1, 1 can only go in r1c1 with r1c2<>1, r1c3<>1 ruled out before."
+        );
+    }
+}
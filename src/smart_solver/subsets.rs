@@ -0,0 +1,196 @@
+use crate::{
+    game,
+    game::{ CandidateGrid, Candidates, Cell, Unit },
+};
+
+use super::logic::{ combinations, Candidate, Reason, Step, Technique };
+
+fn naked_technique(size: usize) -> Technique {
+    match size {
+        2 => Technique::NakedPair,
+        3 => Technique::NakedTriple,
+        _ => Technique::NakedQuad,
+    }
+}
+
+fn hidden_technique(size: usize) -> Technique {
+    match size {
+        2 => Technique::HiddenPair,
+        3 => Technique::HiddenTriple,
+        _ => Technique::HiddenQuad,
+    }
+}
+
+/// `size` cells of a unit holding only `size` digits between them: the
+/// digits go nowhere else in the unit.
+pub(super) fn naked_subset(grid: &CandidateGrid, size: usize) -> Option<Step> {
+    let board = grid.board();
+    for unit in Unit::all() {
+        let empty: Vec<(usize, usize)> = unit.cells().into_iter()
+            .filter(|&(r, c)| board.at(r, c) == Cell::Empty)
+            .collect();
+        // a smaller subset leaves no room for the rest of the unit
+        if empty.len() <= size { continue; }
+
+        let small: Vec<(usize, usize)> = empty.iter().copied()
+            .filter(|&(r, c)| (2..=size).contains(&grid.candidates(r, c).len()))
+            .collect();
+        for cells in combinations(&small, size) {
+            let digits: Candidates = cells.iter()
+                .flat_map(|&(r, c)| grid.candidates(r, c).iter())
+                .collect();
+            if digits.len() != size { continue; }
+
+            let eliminations: Vec<Candidate> = empty.iter()
+                .filter(|cell| !cells.contains(cell))
+                .flat_map(|&(r, c)| {
+                    let others = grid.candidates(r, c);
+                    digits.iter()
+                        .filter(move |&x| others.contains(x))
+                        .map(move |x| Candidate::new((r, c), x))
+                })
+                .collect();
+            if eliminations.is_empty() { continue; }
+
+            return Some(Step {
+                technique: naked_technique(size),
+                placements: Vec::new(),
+                eliminations,
+                focus: cells.clone(),
+                reason: Reason::NakedSubset { unit, cells, digits },
+            });
+        }
+    }
+    None
+}
+
+/// `size` digits of a unit that only go in `size` of its cells: the
+/// cells hold no other digits.
+pub(super) fn hidden_subset(grid: &CandidateGrid, size: usize) -> Option<Step> {
+    for unit in Unit::all() {
+        let placed = unit.cells().into_iter()
+            .filter(|&(r, c)| grid.board().at(r, c) != Cell::Empty)
+            .count();
+        if 9 - placed <= size { continue; }
+
+        let scarce: Vec<_> = game::LEGAL_VALUES.into_iter()
+            .filter(|&x| (2..=size).contains(&grid.cells_with(unit, x).len()))
+            .collect();
+        for combination in combinations(&scarce, size) {
+            let mut cells: Vec<(usize, usize)> = Vec::new();
+            for &x in combination.iter() {
+                for cell in grid.cells_with(unit, x) {
+                    if !cells.contains(&cell) { cells.push(cell); }
+                }
+            }
+            if cells.len() != size { continue; }
+            cells.sort();
+
+            let digits: Candidates = combination.into_iter().collect();
+            let eliminations: Vec<Candidate> = cells.iter()
+                .flat_map(|&(r, c)| {
+                    grid.candidates(r, c).iter()
+                        .filter(|&x| !digits.contains(x))
+                        .map(move |x| Candidate::new((r, c), x))
+                })
+                .collect();
+            if eliminations.is_empty() { continue; }
+
+            return Some(Step {
+                technique: hidden_technique(size),
+                placements: Vec::new(),
+                eliminations,
+                focus: cells.clone(),
+                reason: Reason::HiddenSubset { unit, cells, digits },
+            });
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod subsets_tests {
+    use super::*;
+    use crate::{
+        game::{ Board, Digit, Digit::* },
+        smart_solver::logic::{ logic_tests::assert_sound, LogicalSolver },
+    };
+
+    const PUZZLE: &str = "...8.3..15....4.8...4......6...9..37.....2...3..1.56.9.75....9........1..4..2....";
+
+    fn candidates(digits: &[Digit]) -> Candidates {
+        digits.iter().copied().collect()
+    }
+
+    #[test]
+    fn test_naked_subsets() {
+        let mut grid = CandidateGrid::from_board(&Board::zeroed());
+        grid.set_candidates(0, 3, candidates(&[D1, D2]));
+        grid.set_candidates(0, 7, candidates(&[D2, D3]));
+        assert!(naked_subset(&grid, 2).is_none());
+
+        grid.set_candidates(0, 8, candidates(&[D1, D3]));
+        let step = naked_subset(&grid, 3).unwrap();
+        assert_eq!(step.technique, Technique::NakedTriple);
+        assert_eq!(step.reason, Reason::NakedSubset {
+            unit: Unit::Row(0),
+            cells: vec![(0, 3), (0, 7), (0, 8)],
+            digits: candidates(&[D1, D2, D3]),
+        });
+        // 1, 2 and 3 from the six other cells of the row
+        assert_eq!(step.eliminations.len(), 18);
+        assert!(step.eliminations.iter().all(|e| e.cell.0 == 0 && !step.focus.contains(&e.cell)));
+        assert!(step.to_string().starts_with("Naked triple: in row 1, r1c4,r1c8,r1c9 only hold 123; r1c1<>1, "));
+    }
+
+    #[test]
+    fn test_hidden_subsets() {
+        let mut grid = CandidateGrid::from_board(&Board::zeroed());
+        for c in 2..9 {
+            grid.remove_candidate(0, c, D4);
+            grid.remove_candidate(0, c, D7);
+        }
+        assert!(hidden_subset(&grid, 3).is_none());
+        let step = hidden_subset(&grid, 2).unwrap();
+        assert_eq!(step.technique, Technique::HiddenPair);
+        assert_eq!(step.reason, Reason::HiddenSubset {
+            unit: Unit::Row(0),
+            cells: vec![(0, 0), (0, 1)],
+            digits: candidates(&[D4, D7]),
+        });
+        assert_eq!(step.eliminations.len(), 14);
+        assert!(step.to_string().starts_with("Hidden pair: in row 1, 47 only go in r1c1,r1c2; r1c1<>1, "));
+
+        // nothing left to remove once applied
+        step.apply(&mut grid);
+        assert!(hidden_subset(&grid, 2).is_none());
+    }
+
+    #[test]
+    fn test_solving_with_subsets() {
+        let board: Board = PUZZLE.parse().unwrap();
+        let lower = [
+            Technique::NakedSingle,
+            Technique::HiddenSingle,
+            Technique::Pointing,
+            Technique::Claiming,
+        ];
+        assert!(!LogicalSolver::with_techniques(&lower).solve(&board).is_solved());
+
+        let subsets = [
+            Technique::NakedPair,
+            Technique::HiddenPair,
+            Technique::NakedTriple,
+            Technique::HiddenTriple,
+            Technique::NakedQuad,
+            Technique::HiddenQuad,
+        ];
+        let solution = LogicalSolver::with_techniques(&[&lower[..], &subsets[..]].concat()).solve(&board);
+        assert!(solution.is_solved());
+        assert!(solution.steps.iter().any(|step| matches!(
+            step.reason,
+            Reason::NakedSubset { .. } | Reason::HiddenSubset { .. }
+        )));
+        assert_sound(&board, &solution.steps);
+    }
+}
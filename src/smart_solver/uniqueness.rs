@@ -0,0 +1,340 @@
+use crate::{
+    game,
+    game::{ CandidateGrid, Candidates, Cell, Unit },
+    utilities,
+};
+
+use super::logic::{ combinations, seen_by_all, Candidate, Reason, Step, Technique };
+
+// rectangles of empty cells over exactly two boxes, as
+// [r1c1, r1c2, r2c1, r2c2]
+fn rectangles(grid: &CandidateGrid) -> Vec<[(usize, usize); 4]> {
+    let board = grid.board();
+    let mut rectangles = Vec::new();
+    for r1 in 0..9 {
+        for r2 in r1 + 1..9 {
+            for c1 in 0..9 {
+                for c2 in c1 + 1..9 {
+                    if (r1 / 3 == r2 / 3) == (c1 / 3 == c2 / 3) { continue; }
+                    let corners = [(r1, c1), (r1, c2), (r2, c1), (r2, c2)];
+                    if corners.iter().all(|&(r, c)| board.at(r, c) == Cell::Empty) {
+                        rectangles.push(corners);
+                    }
+                }
+            }
+        }
+    }
+    rectangles
+}
+
+// the units holding both cells
+fn shared_units(a: (usize, usize), b: (usize, usize)) -> Vec<Unit> {
+    Unit::all().filter(|unit| unit.contains(a) && unit.contains(b)).collect()
+}
+
+fn ur_step(
+    technique: Technique,
+    corners: [(usize, usize); 4],
+    digits: Candidates,
+    roof: Vec<(usize, usize)>,
+    extra: Candidates,
+    subset: (Option<Unit>, Vec<(usize, usize)>),
+    eliminations: Vec<Candidate>,
+) -> Step {
+    let (unit, subset) = subset;
+    let mut focus = corners.to_vec();
+    focus.extend(subset.iter().copied());
+    Step {
+        technique,
+        placements: Vec::new(),
+        eliminations,
+        focus,
+        reason: Reason::UniqueRectangle { corners, digits, roof, extra, unit, subset },
+    }
+}
+
+// a rectangle whose corners all hold both `digits`; the `roof` corners
+// hold more than that
+struct Rectangle {
+    corners: [(usize, usize); 4],
+    digits: Candidates,
+    roof: Vec<(usize, usize)>,
+}
+
+// each rectangle with each pair of digits all four corners share
+fn candidate_rectangles(grid: &CandidateGrid) -> Vec<Rectangle> {
+    let mut found = Vec::new();
+    for corners in rectangles(grid) {
+        let common = corners.iter()
+            .fold(Candidates::ALL.mask(), |mask, &(r, c)| mask & grid.candidates(r, c).mask());
+        let common: Vec<_> = Candidates::from_mask(common).iter().collect();
+        for pair in combinations(&common, 2) {
+            let digits: Candidates = pair.into_iter().collect();
+            let roof: Vec<(usize, usize)> = corners.iter().copied()
+                .filter(|&(r, c)| grid.candidates(r, c) != digits)
+                .collect();
+            found.push(Rectangle { corners, digits, roof });
+        }
+    }
+    found
+}
+
+fn extras(grid: &CandidateGrid, cells: &[(usize, usize)], digits: Candidates) -> Candidates {
+    let mask = cells.iter().fold(0, |mask, &(r, c)| mask | grid.candidates(r, c).mask());
+    Candidates::from_mask(mask & !digits.mask())
+}
+
+/// Three corners hold just the pair: the fourth one can't be either.
+pub(super) fn unique_rectangle_1(grid: &CandidateGrid) -> Option<Step> {
+    for Rectangle { corners, digits, roof } in candidate_rectangles(grid) {
+        let [(r, c)] = roof[..] else { continue; };
+        let eliminations = digits.iter().map(|x| Candidate::new((r, c), x)).collect();
+        let extra = extras(grid, &roof, digits);
+        return Some(ur_step(Technique::UniqueRectangle1, corners, digits, roof, extra, (None, Vec::new()), eliminations));
+    }
+    None
+}
+
+// the two roof corners, sharing a line, when the two others hold just
+// the pair
+fn roof_pair(roof: &[(usize, usize)]) -> Option<[(usize, usize); 2]> {
+    let [a, b] = roof[..] else { return None; };
+    (a.0 == b.0 || a.1 == b.1).then_some([a, b])
+}
+
+/// Both roof corners hold the pair and one same extra digit: one of them
+/// is that digit.
+pub(super) fn unique_rectangle_2(grid: &CandidateGrid) -> Option<Step> {
+    for Rectangle { corners, digits, roof } in candidate_rectangles(grid) {
+        let Some([a, b]) = roof_pair(&roof) else { continue; };
+        let (extra_a, extra_b) = (extras(grid, &[a], digits), extras(grid, &[b], digits));
+        if extra_a != extra_b { continue; }
+        let Some(x) = extra_a.single() else { continue; };
+
+        let eliminations = seen_by_all(grid, &[a, b], x);
+        if eliminations.is_empty() { continue; }
+        return Some(ur_step(Technique::UniqueRectangle2, corners, digits, roof, extra_a, (None, Vec::new()), eliminations));
+    }
+    None
+}
+
+/// The extra digits of the roof corners, one of which is true, make a
+/// naked subset with other cells of a unit holding both corners.
+pub(super) fn unique_rectangle_3(grid: &CandidateGrid) -> Option<Step> {
+    for Rectangle { corners, digits, roof } in candidate_rectangles(grid) {
+        let Some([a, b]) = roof_pair(&roof) else { continue; };
+        let extra = extras(grid, &roof, digits);
+
+        for unit in shared_units(a, b) {
+            let others: Vec<(usize, usize)> = unit.cells().into_iter()
+                .filter(|&cell| cell != a && cell != b && grid.board().at(cell.0, cell.1) == Cell::Empty)
+                .collect();
+            for size in 1..=3 {
+                for subset in combinations(&others, size) {
+                    let subset_digits = Candidates::from_mask(
+                        subset.iter().fold(extra.mask(), |mask, &(r, c)| mask | grid.candidates(r, c).mask())
+                    );
+                    if subset_digits.len() != size + 1 { continue; }
+
+                    let eliminations: Vec<Candidate> = others.iter()
+                        .filter(|cell| !subset.contains(cell))
+                        .flat_map(|&(r, c)| subset_digits.iter()
+                            .filter(move |&x| grid.candidates(r, c).contains(x))
+                            .map(move |x| Candidate::new((r, c), x)))
+                        .collect();
+                    if eliminations.is_empty() { continue; }
+
+                    return Some(ur_step(
+                        Technique::UniqueRectangle3, corners, digits, roof.clone(), subset_digits, (Some(unit), subset), eliminations
+                    ));
+                }
+            }
+        }
+    }
+    None
+}
+
+/// One digit of the pair only goes in the roof corners within a unit
+/// holding both: one of them is that digit, so neither is the other one.
+pub(super) fn unique_rectangle_4(grid: &CandidateGrid) -> Option<Step> {
+    for Rectangle { corners, digits, roof } in candidate_rectangles(grid) {
+        let Some([a, b]) = roof_pair(&roof) else { continue; };
+        for unit in shared_units(a, b) {
+            for locked in digits.iter() {
+                if grid.cells_with(unit, locked) != [a, b] { continue; }
+
+                let other = Candidates::from_mask(digits.mask() & !locked.bit());
+                let x = other.single().unwrap();
+                let eliminations = vec![Candidate::new(a, x), Candidate::new(b, x)];
+                return Some(ur_step(Technique::UniqueRectangle4, corners, digits, roof, other, (Some(unit), Vec::new()), eliminations));
+            }
+        }
+    }
+    None
+}
+
+/// Bivalue universal grave plus one: every unsolved cell has two
+/// candidates but one, which has three. Without its extra digit, every
+/// digit would have two places in every unit and the puzzle two
+/// solutions, so the cell is that digit: the one with three places in
+/// the cell's row.
+pub(super) fn bug_plus_one(grid: &CandidateGrid) -> Option<Step> {
+    let empty: Vec<(usize, usize)> = (0..81)
+        .map(utilities::coords_from_pos)
+        .filter(|&(r, c)| grid.board().at(r, c) == Cell::Empty)
+        .collect();
+    let mut trivalue = None;
+    for &(r, c) in empty.iter() {
+        match grid.candidates(r, c).len() {
+            2 => {},
+            3 if trivalue.is_none() => trivalue = Some((r, c)),
+            _ => return None,
+        }
+    }
+    let (r, c) = trivalue?;
+
+    let digit = grid.candidates(r, c).iter().find(|&x| grid.cells_with(Unit::Row(r), x).len() == 3)?;
+    // every other digit has to be left twice in every unit
+    let mut bug = *grid;
+    bug.remove_candidate(r, c, digit);
+    let is_grave = Unit::all().all(|unit| {
+        game::LEGAL_VALUES.into_iter().all(|x| matches!(bug.cells_with(unit, x).len(), 0 | 2))
+    });
+    if !is_grave { return None; }
+
+    Some(Step {
+        technique: Technique::BugPlusOne,
+        placements: vec![Candidate::new((r, c), digit)],
+        eliminations: Vec::new(),
+        focus: vec![(r, c)],
+        reason: Reason::BugPlusOne { cell: (r, c), unit: Unit::Row(r) },
+    })
+}
+
+#[cfg(test)]
+mod uniqueness_tests {
+    use super::*;
+    use crate::{
+        game::{ Board, Digit::* },
+        smart_solver::logic::{ logic_tests::{ assert_sound, set }, LogicalSolver },
+    };
+
+    const PUZZLE: &str = "4.......7.9...7..48..19..2.1.7..........3..58...5........8..4...25...3...8...12..";
+    const BUG_PUZZLE: &str = "...79.5......65.2..65...7.9..9..3.....361......1.82.565..42.1...........4.7.....8";
+
+    fn pair() -> Candidates {
+        [D1, D2].into_iter().collect()
+    }
+
+    #[test]
+    fn test_type_1() {
+        let mut grid = CandidateGrid::from_board(&Board::zeroed());
+        set(&mut grid, (0, 0), &[D1, D2]);
+        set(&mut grid, (0, 3), &[D1, D2]);
+        set(&mut grid, (2, 0), &[D1, D2]);
+        set(&mut grid, (2, 3), &[D1, D2, D5]);
+        let step = unique_rectangle_1(&grid).unwrap();
+        assert_eq!(step.eliminations, vec![Candidate::new((2, 3), D1), Candidate::new((2, 3), D2)]);
+        assert_eq!(
+            step.to_string(),
+            "Unique rectangle type 1: r1c1,r1c4,r3c1,r3c4 would hold 12 twice over, \
+             so r3c4 holds something else; r3c4<>1, r3c4<>2."
+        );
+    }
+
+    #[test]
+    fn test_type_2() {
+        let mut grid = CandidateGrid::from_board(&Board::zeroed());
+        set(&mut grid, (0, 0), &[D1, D2]);
+        set(&mut grid, (0, 3), &[D1, D2]);
+        set(&mut grid, (2, 0), &[D1, D2, D7]);
+        set(&mut grid, (2, 3), &[D1, D2, D7]);
+        assert!(unique_rectangle_1(&grid).is_none());
+        let step = unique_rectangle_2(&grid).unwrap();
+        assert_eq!(step.reason, Reason::UniqueRectangle {
+            corners: [(0, 0), (0, 3), (2, 0), (2, 3)],
+            digits: pair(),
+            roof: vec![(2, 0), (2, 3)],
+            extra: [D7].into_iter().collect(),
+            unit: None,
+            subset: Vec::new(),
+        });
+        assert_eq!(step.eliminations, [1, 2, 4, 5, 6, 7, 8].map(|c| Candidate::new((2, c), D7)));
+        assert!(step.to_string().starts_with(
+            "Unique rectangle type 2: r1c1,r1c4,r3c1,r3c4 would hold 12 twice over, \
+             so one of r3c1,r3c4 is 7;"
+        ));
+    }
+
+    #[test]
+    fn test_type_3() {
+        let mut grid = CandidateGrid::from_board(&Board::zeroed());
+        set(&mut grid, (0, 0), &[D1, D2]);
+        set(&mut grid, (0, 3), &[D1, D2]);
+        set(&mut grid, (2, 0), &[D1, D2, D5]);
+        set(&mut grid, (2, 3), &[D1, D2, D6]);
+        set(&mut grid, (2, 6), &[D5, D6]);
+        assert!(unique_rectangle_2(&grid).is_none());
+        let step = unique_rectangle_3(&grid).unwrap();
+        assert_eq!(step.reason, Reason::UniqueRectangle {
+            corners: [(0, 0), (0, 3), (2, 0), (2, 3)],
+            digits: pair(),
+            roof: vec![(2, 0), (2, 3)],
+            extra: [D5, D6].into_iter().collect(),
+            unit: Some(Unit::Row(2)),
+            subset: vec![(2, 6)],
+        });
+        assert_eq!(step.eliminations.len(), 12);
+        assert!(step.to_string().starts_with(
+            "Unique rectangle type 3: r1c1,r1c4,r3c1,r3c4 would hold 12 twice over, \
+             so r3c1,r3c4 and r3c7 only hold 56 in row 3;"
+        ));
+    }
+
+    #[test]
+    fn test_type_4() {
+        let mut grid = CandidateGrid::from_board(&Board::zeroed());
+        set(&mut grid, (0, 0), &[D1, D2]);
+        set(&mut grid, (0, 3), &[D1, D2]);
+        set(&mut grid, (2, 0), &[D1, D2, D5]);
+        set(&mut grid, (2, 3), &[D1, D2, D6]);
+        for c in [1, 2, 4, 5, 6, 7, 8] {
+            grid.remove_candidate(2, c, D1);
+        }
+        let step = unique_rectangle_4(&grid).unwrap();
+        assert_eq!(step.eliminations, vec![Candidate::new((2, 0), D2), Candidate::new((2, 3), D2)]);
+        assert_eq!(
+            step.to_string(),
+            "Unique rectangle type 4: r1c1,r1c4,r3c1,r3c4 would hold 12 twice over, \
+             and 1 only goes in r3c1,r3c4 in row 3, so neither is 2; r3c1<>2, r3c4<>2."
+        );
+    }
+
+    #[test]
+    fn test_off_by_default() {
+        assert!(Technique::ALL.contains(&Technique::UniqueRectangle1));
+        assert!(!LogicalSolver::default().techniques.iter().any(|t| t.assumes_uniqueness()));
+        let solver = LogicalSolver::default().assuming_uniqueness();
+        assert_eq!(solver.techniques, Technique::ALL);
+    }
+
+    #[test]
+    fn test_solving_with_uniqueness() {
+        let board: Board = PUZZLE.parse().unwrap();
+        let solver = LogicalSolver::default().assuming_uniqueness();
+        let without_chains: Vec<Technique> = LogicalSolver::default().techniques.into_iter()
+            .filter(|t| t.difficulty() <= Technique::FinnedJellyfish.difficulty())
+            .collect();
+        assert!(!LogicalSolver::with_techniques(&without_chains).solve(&board).is_solved());
+        let solution = solver.solve(&board);
+        assert!(solution.is_solved());
+        assert!(solution.steps.iter().any(|step| step.technique == Technique::UniqueRectangle4));
+        assert_sound(&board, &solution.steps);
+
+        let board: Board = BUG_PUZZLE.parse().unwrap();
+        let solution = solver.solve(&board);
+        assert!(solution.is_solved());
+        assert!(solution.steps.iter().any(|step| step.technique == Technique::BugPlusOne));
+        assert_sound(&board, &solution.steps);
+    }
+}
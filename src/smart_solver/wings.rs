@@ -0,0 +1,206 @@
+use crate::{
+    game::{ CandidateGrid, Candidates, Digit, Unit },
+    utilities,
+};
+
+use super::logic::{ seen_by_all, Reason, Step, Technique };
+
+fn cells_with_len(grid: &CandidateGrid, len: usize) -> Vec<(usize, usize)> {
+    (0..81)
+        .map(utilities::coords_from_pos)
+        .filter(|&(r, c)| grid.candidates(r, c).len() == len)
+        .collect()
+}
+
+fn candidates(grid: &CandidateGrid, (r, c): (usize, usize)) -> Candidates {
+    grid.candidates(r, c)
+}
+
+fn wing_step(
+    grid: &CandidateGrid,
+    technique: Technique,
+    pivot: (usize, usize),
+    pincers: [(usize, usize); 2],
+    digit: Digit,
+) -> Option<Step> {
+    let eliminations = match technique {
+        Technique::XYZWing => seen_by_all(grid, &[pivot, pincers[0], pincers[1]], digit),
+        _ => seen_by_all(grid, &pincers, digit),
+    };
+    if eliminations.is_empty() { return None; }
+
+    Some(Step {
+        technique,
+        placements: Vec::new(),
+        eliminations,
+        focus: vec![pivot, pincers[0], pincers[1]],
+        reason: Reason::Wing { pivot, pincers, digit },
+    })
+}
+
+/// A bivalue pivot `xy` seeing pincers `xz` and `yz`: whichever digit
+/// the pivot takes, one pincer is `z`.
+pub(super) fn xy_wing(grid: &CandidateGrid) -> Option<Step> {
+    let bivalue = cells_with_len(grid, 2);
+    for &pivot in bivalue.iter() {
+        let digits = candidates(grid, pivot);
+        let pincers: Vec<(usize, usize)> = bivalue.iter().copied()
+            .filter(|&cell| utilities::are_peers(pivot, cell))
+            .filter(|&cell| (candidates(grid, cell).mask() & digits.mask()).count_ones() == 1)
+            .collect();
+        for (i, &first) in pincers.iter().enumerate() {
+            for &second in pincers[i + 1..].iter() {
+                let (a, b) = (candidates(grid, first), candidates(grid, second));
+                // z is the digit the pincers share, the pivot's two being split between them
+                let z = Candidates::from_mask(a.mask() & b.mask() & !digits.mask());
+                let Some(z) = z.single() else { continue; };
+                if Candidates::from_mask((a.mask() | b.mask()) & !z.bit()) != digits { continue; }
+
+                if let Some(step) = wing_step(grid, Technique::XYWing, pivot, [first, second], z) {
+                    return Some(step);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// A trivalue pivot `xyz` seeing pincers `xz` and `yz`: one of the
+/// three cells is `z`.
+pub(super) fn xyz_wing(grid: &CandidateGrid) -> Option<Step> {
+    let bivalue = cells_with_len(grid, 2);
+    for pivot in cells_with_len(grid, 3) {
+        let digits = candidates(grid, pivot);
+        let pincers: Vec<(usize, usize)> = bivalue.iter().copied()
+            .filter(|&cell| utilities::are_peers(pivot, cell))
+            .filter(|&cell| candidates(grid, cell).mask() & !digits.mask() == 0)
+            .collect();
+        for (i, &first) in pincers.iter().enumerate() {
+            for &second in pincers[i + 1..].iter() {
+                let (a, b) = (candidates(grid, first), candidates(grid, second));
+                if a == b { continue; }
+                let Some(z) = Candidates::from_mask(a.mask() & b.mask()).single() else { continue; };
+
+                if let Some(step) = wing_step(grid, Technique::XYZWing, pivot, [first, second], z) {
+                    return Some(step);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Two bivalue cells `xy` that don't see each other, joined by a strong
+/// link on `x`: a unit where `x` only goes in two cells, one seeing each.
+/// One of the two cells is `x`, so the other is `y`.
+pub(super) fn w_wing(grid: &CandidateGrid) -> Option<Step> {
+    let bivalue = cells_with_len(grid, 2);
+    for (i, &first) in bivalue.iter().enumerate() {
+        let digits = candidates(grid, first);
+        for &second in bivalue[i + 1..].iter() {
+            if candidates(grid, second) != digits || utilities::are_peers(first, second) { continue; }
+
+            for x in digits.iter() {
+                let y = Candidates::from_mask(digits.mask() & !x.bit()).single().unwrap();
+                for unit in Unit::all() {
+                    let [a, b] = grid.cells_with(unit, x)[..] else { continue; };
+                    let link = if utilities::are_peers(a, first) && utilities::are_peers(b, second) {
+                        [a, b]
+                    } else if utilities::are_peers(b, first) && utilities::are_peers(a, second) {
+                        [b, a]
+                    } else {
+                        continue;
+                    };
+                    if link.contains(&first) || link.contains(&second) { continue; }
+
+                    let eliminations = seen_by_all(grid, &[first, second], y);
+                    if eliminations.is_empty() { continue; }
+
+                    return Some(Step {
+                        technique: Technique::WWing,
+                        placements: Vec::new(),
+                        eliminations,
+                        focus: vec![first, second, link[0], link[1]],
+                        reason: Reason::WWing { pincers: [first, second], digits, link: unit, pivot: link, link_digit: x },
+                    });
+                }
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod wings_tests {
+    use super::*;
+    use crate::{
+        game::{ Board, Digit::* },
+        smart_solver::logic::{ logic_tests::{ assert_sound, set }, Candidate, LogicalSolver },
+    };
+
+    const PUZZLE: &str = "2..3....46.3..4.8.....583..3...27....98..67.....8..1...4.......1.6...........5.62";
+
+    #[test]
+    fn test_xy_wing() {
+        let mut grid = CandidateGrid::from_board(&Board::zeroed());
+        set(&mut grid, (0, 0), &[D1, D2]);
+        set(&mut grid, (0, 4), &[D1, D3]);
+        set(&mut grid, (4, 0), &[D2, D3]);
+        let step = xy_wing(&grid).unwrap();
+        assert_eq!(step.reason, Reason::Wing { pivot: (0, 0), pincers: [(0, 4), (4, 0)], digit: D3 });
+        assert_eq!(step.eliminations, vec![Candidate::new((4, 4), D3)]);
+        assert_eq!(step.to_string(), "XY-Wing: pivot r1c1 with pincers r1c5,r5c1, one of which is 3; r5c5<>3.");
+
+        // with a pincer sharing both digits of the pivot there's no wing
+        set(&mut grid, (4, 0), &[D1, D2]);
+        assert!(xy_wing(&grid).is_none());
+    }
+
+    #[test]
+    fn test_xyz_wing() {
+        let mut grid = CandidateGrid::from_board(&Board::zeroed());
+        set(&mut grid, (0, 0), &[D1, D2, D3]);
+        set(&mut grid, (0, 1), &[D1, D3]);
+        set(&mut grid, (3, 0), &[D2, D3]);
+        assert!(xy_wing(&grid).is_none());
+        let step = xyz_wing(&grid).unwrap();
+        assert_eq!(step.reason, Reason::Wing { pivot: (0, 0), pincers: [(0, 1), (3, 0)], digit: D3 });
+        assert_eq!(step.eliminations, vec![Candidate::new((1, 0), D3), Candidate::new((2, 0), D3)]);
+        assert!(step.to_string().starts_with("XYZ-Wing: pivot r1c1 with pincers r1c2,r4c1, one of the three is 3;"));
+    }
+
+    #[test]
+    fn test_w_wing() {
+        let mut grid = CandidateGrid::from_board(&Board::zeroed());
+        set(&mut grid, (0, 0), &[D1, D2]);
+        set(&mut grid, (4, 4), &[D1, D2]);
+        assert!(w_wing(&grid).is_none());
+
+        for c in [1, 2, 3, 5, 6, 7, 8] {
+            grid.remove_candidate(8, c, D1);
+        }
+        let step = w_wing(&grid).unwrap();
+        assert_eq!(step.reason, Reason::WWing {
+            pincers: [(0, 0), (4, 4)],
+            digits: [D1, D2].into_iter().collect(),
+            link: Unit::Row(8),
+            pivot: [(8, 0), (8, 4)],
+            link_digit: D1,
+        });
+        assert_eq!(step.eliminations, vec![Candidate::new((0, 4), D2), Candidate::new((4, 0), D2)]);
+        assert_eq!(
+            step.to_string(),
+            "W-Wing: r1c1 and r5c5 are both 12, and 1 in row 9 only goes in r9c1,r9c5, \
+             so one of them is 2; r1c5<>2, r5c1<>2."
+        );
+    }
+
+    #[test]
+    fn test_solving_with_wings() {
+        let board: Board = PUZZLE.parse().unwrap();
+        let solution = LogicalSolver::default().solve(&board);
+        assert!(solution.is_solved());
+        assert_eq!(solution.hardest(), Some(Technique::XYWing));
+        assert_sound(&board, &solution.steps);
+    }
+}
@@ -0,0 +1,416 @@
+use std::{
+    error::Error,
+    fmt,
+    fmt::Debug,
+    sync::{
+        Arc,
+        atomic::{ AtomicBool, AtomicU64, Ordering },
+    },
+    time::{ Duration, Instant },
+};
+
+use crate::{
+    dlx_solver::DlxSolver,
+    game::Board,
+    naive_solver::NaiveSolver,
+    parallel_solver::ParallelSolver,
+    smart_solver::SmartSolver,
+};
+
+// one result per search, not worth boxing the boards for
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, Copy)]
+pub enum SolverResult {
+    /// The puzzle is contradictory
+    NoSolution,
+    Unique(Board),
+    /// The puzzle is ambiguous; these are the first two solutions found
+    Multiple { first: Board, second: Board },
+}
+
+impl SolverResult {
+    pub fn from_solutions(solutions: &[Board]) -> Self {
+        match solutions {
+            [] => SolverResult::NoSolution,
+            [solution] => SolverResult::Unique(*solution),
+            [first, second, ..] => SolverResult::Multiple {
+                first: *first,
+                second: *second,
+            },
+        }
+    }
+
+    /// Cells holding different digits in the two solutions of an
+    /// ambiguous puzzle, empty otherwise.
+    pub fn differing_cells(&self) -> Vec<(usize, usize)> {
+        let SolverResult::Multiple { first, second } = self else {
+            return Vec::new();
+        };
+        (0..9)
+            .flat_map(|r| (0..9).map(move |c| (r, c)))
+            .filter(|&(r, c)| first.at(r, c).digit() != second.at(r, c).digit())
+            .collect()
+    }
+}
+
+/// How much work a search took. Each backend counts in its own terms:
+/// a node is one visited partial assignment and a backtrack is a node
+/// abandoned as a dead end.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SolverStats {
+    pub nodes: u64,
+    pub backtracks: u64,
+    pub max_depth: usize,
+    pub candidates_tried: u64,
+    pub elapsed: Duration,
+}
+
+impl SolverStats {
+    pub fn visit(&mut self, depth: usize) {
+        self.nodes += 1;
+        self.max_depth = self.max_depth.max(depth);
+    }
+
+    /// Adds up the work of a search run in separate pieces.
+    pub fn merge(&mut self, other: &SolverStats) {
+        self.nodes += other.nodes;
+        self.backtracks += other.backtracks;
+        self.max_depth = self.max_depth.max(other.max_depth);
+        self.candidates_tried += other.candidates_tried;
+        self.elapsed += other.elapsed;
+    }
+}
+
+impl fmt::Display for SolverStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "nodes: {}, backtracks: {}, max depth: {}, candidates tried: {}, time: {:?}",
+            self.nodes, self.backtracks, self.max_depth, self.candidates_tried, self.elapsed
+        )
+    }
+}
+
+/// The search gave up because its budget ran out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BudgetExhausted;
+
+impl fmt::Display for BudgetExhausted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "search budget exhausted")
+    }
+}
+
+impl Error for BudgetExhausted {}
+
+// how many nodes go by between two looks at the clock
+const DEADLINE_CHECK_INTERVAL: u64 = 64;
+
+/// Limits on the work a search may do: a deadline, a number of search
+/// nodes and a flag that cancels it from another thread. Clones share the
+/// node count and the flag, so one budget can cover several searches,
+/// e.g. all the uniqueness checks made while generating a puzzle.
+#[derive(Debug, Clone, Default)]
+pub struct Budget {
+    deadline: Option<Instant>,
+    max_nodes: Option<u64>,
+    nodes: Arc<AtomicU64>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Budget {
+    /// A budget that only runs out when cancelled.
+    pub fn unlimited() -> Self {
+        Budget::default()
+    }
+
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.with_deadline(Instant::now() + timeout)
+    }
+
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    pub fn with_max_nodes(mut self, max_nodes: u64) -> Self {
+        self.max_nodes = Some(max_nodes);
+        self
+    }
+
+    /// Uses `flag` as the cancellation flag, so whoever holds it can stop
+    /// the searches by setting it.
+    pub fn with_cancel_flag(mut self, flag: Arc<AtomicBool>) -> Self {
+        self.cancelled = flag;
+        self
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Nodes spent so far. Only budgets with a node limit or a deadline
+    /// keep count, so that unlimited searches on several threads don't
+    /// all write to the same counter.
+    pub fn nodes_spent(&self) -> u64 {
+        self.nodes.load(Ordering::Relaxed)
+    }
+
+    /// Fails if the deadline has passed or the budget was cancelled.
+    pub fn check(&self) -> Result<(), BudgetExhausted> {
+        if self.cancelled.load(Ordering::Relaxed)
+            || self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return Err(BudgetExhausted);
+        }
+        Ok(())
+    }
+
+    /// Accounts for one more search node, failing once the budget is
+    /// used up. The clock is only read every few nodes.
+    pub fn spend_node(&self) -> Result<(), BudgetExhausted> {
+        if self.cancelled.load(Ordering::Relaxed) { return Err(BudgetExhausted); }
+        if self.max_nodes.is_none() && self.deadline.is_none() { return Ok(()); }
+
+        let spent = self.nodes.fetch_add(1, Ordering::Relaxed) + 1;
+        if self.max_nodes.is_some_and(|max_nodes| spent > max_nodes) {
+            return Err(BudgetExhausted);
+        }
+        if spent.is_multiple_of(DEADLINE_CHECK_INTERVAL) { self.check()?; }
+        Ok(())
+    }
+}
+
+// a fresh unlimited budget is never cancelled, so searches under it
+// always run to completion
+pub(crate) fn unbounded<T>(result: Result<T, BudgetExhausted>) -> T {
+    result.expect("an unlimited budget ran out")
+}
+
+/// Common interface of the solving backends. Filled cells, givens and
+/// non-givens alike, are taken as fixed.
+pub trait Solver : Debug {
+    /// Collects solutions of `board` with the empty cells filled as
+    /// non-givens, stopping once `limit` of them have been found.
+    /// The work done is added to `stats`.
+    fn search_solutions(
+        &self,
+        board: &Board,
+        limit: usize,
+        budget: &Budget,
+        stats: &mut SolverStats,
+    ) -> Result<Vec<Board>, BudgetExhausted>;
+
+    /// Counts the solutions of `board`, stopping once `limit` of them
+    /// have been found. The work done is added to `stats`.
+    fn search_count(
+        &self,
+        board: &Board,
+        limit: u64,
+        budget: &Budget,
+        stats: &mut SolverStats,
+    ) -> Result<u64, BudgetExhausted>;
+
+    fn collect_solutions(&self, board: &Board, limit: usize) -> Vec<Board> {
+        unbounded(self.search_solutions(board, limit, &Budget::unlimited(), &mut SolverStats::default()))
+    }
+
+    fn count_solutions(&self, board: &Board, limit: u64) -> u64 {
+        unbounded(self.search_count(board, limit, &Budget::unlimited(), &mut SolverStats::default()))
+    }
+
+    /// Returns a solution of `board`, or `None` if there is none.
+    fn solve(&self, board: &Board) -> Option<Board> {
+        self.collect_solutions(board, 1).pop()
+    }
+
+    fn analyse(&self, board: &Board) -> SolverResult {
+        unbounded(self.analyse_within(board, &Budget::unlimited()))
+    }
+
+    fn analyse_within(&self, board: &Board, budget: &Budget) -> Result<SolverResult, BudgetExhausted> {
+        let solutions = self.search_solutions(board, 2, budget, &mut SolverStats::default())?;
+        Ok(SolverResult::from_solutions(&solutions))
+    }
+
+    fn analyse_with_stats(&self, board: &Board, budget: &Budget) -> (Result<SolverResult, BudgetExhausted>, SolverStats) {
+        let mut stats = SolverStats::default();
+        let start = Instant::now();
+        let solutions = self.search_solutions(board, 2, budget, &mut stats);
+        stats.elapsed = start.elapsed();
+
+        (solutions.map(|solutions| SolverResult::from_solutions(&solutions)), stats)
+    }
+
+    fn count_solutions_with_stats(
+        &self,
+        board: &Board,
+        limit: u64,
+        budget: &Budget,
+    ) -> (Result<u64, BudgetExhausted>, SolverStats) {
+        let mut stats = SolverStats::default();
+        let start = Instant::now();
+        let count = self.search_count(board, limit, budget, &mut stats);
+        stats.elapsed = start.elapsed();
+
+        (count, stats)
+    }
+
+    fn is_uniquely_solvable(&self, board: &Board) -> bool {
+        self.count_solutions(board, 2) == 1
+    }
+
+    fn is_uniquely_solvable_within(&self, board: &Board, budget: &Budget) -> Result<bool, BudgetExhausted> {
+        let count = self.search_count(board, 2, budget, &mut SolverStats::default())?;
+        Ok(count == 1)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SolverKind {
+    /// Backtracking on the cell with the fewest candidates
+    #[default]
+    Naive,
+    /// Constraint propagation with fewest-candidates branching
+    Smart,
+    /// Dancing Links exact cover
+    Dlx,
+    /// Smart solver sharing subtrees between threads
+    Parallel,
+}
+
+impl SolverKind {
+    pub fn solver(self) -> Box<dyn Solver> {
+        match self {
+            SolverKind::Naive => Box::new(NaiveSolver),
+            SolverKind::Smart => Box::new(SmartSolver),
+            SolverKind::Dlx => Box::new(DlxSolver),
+            SolverKind::Parallel => Box::new(ParallelSolver::default()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod solver_tests {
+    use super::*;
+    use crate::evaluator;
+
+    const ALL_KINDS: [SolverKind; 4] = [
+        SolverKind::Naive,
+        SolverKind::Smart,
+        SolverKind::Dlx,
+        SolverKind::Parallel,
+    ];
+
+    #[test]
+    fn test_backends_agree() {
+        let puzzles = [
+            ("53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79", 1),
+            (".7.415369691783542435926781763258194.5.194637149637258387542916516879423924361875", 2),
+            ("872415369691783542435926781763258194258194637149637258...........................", 96),
+            ("11...............................................................................", 0),
+            ("12345678.........9...............................................................", 0),
+            ("872415369691783542435926781763258194258194637149637258387542916516879423924361875", 1),
+        ];
+        for (puzzle, count) in puzzles {
+            let board: Board = puzzle.parse().unwrap();
+            for kind in ALL_KINDS {
+                let solver = kind.solver();
+                assert_eq!(solver.count_solutions(&board, u64::MAX), count, "{:?}", kind);
+                assert_eq!(solver.count_solutions(&board, 2), count.min(2), "{:?}", kind);
+                assert_eq!(solver.is_uniquely_solvable(&board), count == 1, "{:?}", kind);
+
+                let solution = solver.solve(&board);
+                assert_eq!(solution.is_some(), count > 0, "{:?}", kind);
+                if let Some(solution) = solution {
+                    assert!(evaluator::evaluate(&solution), "{:?}", kind);
+                }
+
+                match solver.analyse(&board) {
+                    SolverResult::NoSolution => assert_eq!(count, 0),
+                    SolverResult::Unique(solution) => {
+                        assert_eq!(count, 1);
+                        assert!(evaluator::evaluate(&solution));
+                    },
+                    SolverResult::Multiple { first, second } => {
+                        assert!(count >= 2);
+                        assert!(evaluator::evaluate(&first));
+                        assert!(evaluator::evaluate(&second));
+                    },
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_differing_cells() {
+        let board: Board = ".7.415369691783542435926781763258194.5.194637149637258387542916516879423924361875"
+            .parse().unwrap();
+        for kind in ALL_KINDS {
+            let result = kind.solver().analyse(&board);
+            assert!(matches!(result, SolverResult::Multiple { .. }));
+            assert_eq!(result.differing_cells(), vec![(0, 0), (0, 2), (4, 0), (4, 2)]);
+        }
+
+        let board: Board = "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79"
+            .parse().unwrap();
+        let result = SolverKind::Smart.solver().analyse(&board);
+        assert!(matches!(result, SolverResult::Unique(_)));
+        assert!(result.differing_cells().is_empty());
+    }
+
+    #[test]
+    fn test_stats() {
+        let board: Board = "1....7.9..3..2...8..96..5....53..9...1..8...26....4...3......1..4......7..7...3.."
+            .parse().unwrap();
+        for kind in ALL_KINDS {
+            let (result, stats) = kind.solver().analyse_with_stats(&board, &Budget::unlimited());
+            assert!(matches!(result, Ok(SolverResult::Unique(_))));
+            assert!(stats.nodes > 1, "{:?}", kind);
+            assert!(stats.backtracks > 0, "{:?}", kind);
+            assert!(stats.max_depth > 0, "{:?}", kind);
+            assert!(stats.candidates_tried >= stats.nodes - 1, "{:?}", kind);
+        }
+
+        // nothing to search in a full grid
+        let board: Board = "872415369691783542435926781763258194258194637149637258387542916516879423924361875"
+            .parse().unwrap();
+        for kind in ALL_KINDS {
+            let (count, stats) = kind.solver().count_solutions_with_stats(&board, 2, &Budget::unlimited());
+            assert_eq!(count, Ok(1));
+            assert_eq!(stats.nodes, 1, "{:?}", kind);
+            assert_eq!(stats.max_depth, 0, "{:?}", kind);
+            assert_eq!(stats.backtracks, 0, "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn test_budget() {
+        let board: Board = "1....7.9..3..2...8..96..5....53..9...1..8...26....4...3......1..4......7..7...3.."
+            .parse().unwrap();
+        for kind in ALL_KINDS {
+            let solver = kind.solver();
+
+            let budget = Budget::unlimited().with_max_nodes(10);
+            assert_eq!(solver.analyse_within(&board, &budget).err(), Some(BudgetExhausted), "{:?}", kind);
+            assert_eq!(solver.is_uniquely_solvable_within(&board, &budget), Err(BudgetExhausted), "{:?}", kind);
+
+            let budget = Budget::unlimited();
+            budget.cancel();
+            assert_eq!(solver.is_uniquely_solvable_within(&board, &budget), Err(BudgetExhausted), "{:?}", kind);
+
+            let budget = Budget::unlimited().with_timeout(Duration::ZERO);
+            assert_eq!(solver.is_uniquely_solvable_within(&board, &budget), Err(BudgetExhausted), "{:?}", kind);
+        }
+
+        // a budget big enough changes nothing, and clones share what is spent
+        let board: Board = "872415369691783542435926781763258194258194637149637258..........................."
+            .parse().unwrap();
+        for kind in ALL_KINDS {
+            let budget = Budget::unlimited().with_max_nodes(1_000_000);
+            let (count, stats) = kind.solver().count_solutions_with_stats(&board, u64::MAX, &budget.clone());
+            assert_eq!(count, Ok(96), "{:?}", kind);
+            assert_eq!(budget.nodes_spent(), stats.nodes, "{:?}", kind);
+        }
+    }
+}
@@ -18,18 +18,20 @@ struct BoardWidget<'a> {
     empty_value: Option<char>,
     show_non_givens: bool,
     position: Option<(usize, usize)>,
+    highlighted: Vec<(usize, usize)>,
 }
 
 impl<'a> BoardWidget<'a> {
     pub fn new(board: &'a Board) -> Self {
         BoardWidget {
-            board: board,
+            board,
             given_style: None,
             non_given_style: None,
             empty_style: None,
             empty_value: None,
             show_non_givens: true,
             position: None,
+            highlighted: Vec::new(),
         }
     }
 
@@ -62,6 +64,23 @@ impl<'a> BoardWidget<'a> {
         self.position = position;
         self
     }
+
+    pub fn highlighted(mut self, cells: &[(usize, usize)]) -> Self {
+        self.highlighted = cells.to_vec();
+        self
+    }
+
+    // the cursor stands out the most, then the highlighted cells
+    fn cell_style(&self, style: Option<Style>, cell: (usize, usize)) -> Style {
+        let style = style.unwrap_or_default();
+        if self.position == Some(cell) {
+            style.bg(Color::Magenta).fg(Color::Black)
+        } else if self.highlighted.contains(&cell) {
+            style.bg(Color::Yellow).fg(Color::Black)
+        } else {
+            style
+        }
+    }
 }
 
 impl<'a> Widget for &'a BoardWidget<'a> {
@@ -70,7 +89,7 @@ impl<'a> Widget for &'a BoardWidget<'a> {
         let mut lines: Vec<Line> = Vec::new();
 
         let top_border = format!("{}{}{}{}{}{}{}",
-            "┏".to_string(),
+            "┏",
             &"━".repeat(7), "┳",
             &"━".repeat(7), "┳",
             &"━".repeat(7), "┓"
@@ -78,7 +97,7 @@ impl<'a> Widget for &'a BoardWidget<'a> {
         lines.push(Line::from(vec![Span::raw(top_border)]));
 
         let sep = format!("{}{}{}{}{}{}{}",
-            "┣".to_string(),
+            "┣",
             "━".repeat(7), "╋",
             "━".repeat(7), "╋",
             "━".repeat(7), "┫"
@@ -90,13 +109,7 @@ impl<'a> Widget for &'a BoardWidget<'a> {
             for (col, cell) in self.board.row_cells(row) {
                 match cell {
                     Cell::Given(ch) => {
-                        let mut style = self.given_style
-                            .unwrap_or(Style::default());
-                        if let Some(pos) = self.position && pos == (row,col) {
-                            style = style
-                                .bg(Color::Magenta)
-                                .fg(Color::Black);
-                        }
+                        let style = self.cell_style(self.given_style, (row, col));
                         line.push(Span::styled(
                             ch.to_string().clone(),
                             style
@@ -104,25 +117,13 @@ impl<'a> Widget for &'a BoardWidget<'a> {
                     },
                     Cell::NonGiven(ch) => {
                         if self.show_non_givens {
-                            let mut style = self.non_given_style
-                                .unwrap_or(Style::default());
-                            if let Some(pos) = self.position && pos == (row,col) {
-                                style = style
-                                    .bg(Color::Magenta)
-                                    .fg(Color::Black);
-                            }
+                            let style = self.cell_style(self.non_given_style, (row, col));
                             line.push(Span::styled(
                                 ch.to_string().clone(),
                                 style
                             ));
                         } else {
-                            let mut style = self.empty_style
-                                .unwrap_or(Style::default());
-                            if let Some(pos) = self.position && pos == (row,col) {
-                                style = style
-                                    .bg(Color::Magenta)
-                                    .fg(Color::Black);
-                            }
+                            let style = self.cell_style(self.empty_style, (row, col));
                             line.push(Span::styled(
                                 self.empty_value.unwrap_or('_').to_string().clone(),
                                 style
@@ -130,13 +131,7 @@ impl<'a> Widget for &'a BoardWidget<'a> {
                         }
                     },
                     Cell::Empty => {
-                        let mut style = self.empty_style
-                            .unwrap_or(Style::default());
-                        if let Some(pos) = self.position && pos == (row,col) {
-                            style = style
-                                .bg(Color::Magenta)
-                                .fg(Color::Black);
-                        }
+                        let style = self.cell_style(self.empty_style, (row, col));
                         line.push(Span::styled(
                             self.empty_value.unwrap_or('_').to_string().clone(),
                             style
@@ -158,7 +153,7 @@ impl<'a> Widget for &'a BoardWidget<'a> {
         }
 
         let bottom_border = format!("{}{}{}{}{}{}{}",
-            "┗".to_string(),
+            "┗",
             &"━".repeat(7), "┻",
             &"━".repeat(7), "┻",
             &"━".repeat(7), "┛"
@@ -187,13 +182,18 @@ impl<'a> PuzzleBoardWidget<'a> {
                     .fg(Color::Gray)
             )
             .show_non_givens(true);
-        Self { board_widget: board_widget }
+        Self { board_widget }
     }
 
     pub fn set_position(mut self, position: Option<(usize, usize)>) -> Self {
         self.board_widget = self.board_widget.position(position);
         self
     }
+
+    pub fn set_highlighted(mut self, cells: &[(usize, usize)]) -> Self {
+        self.board_widget = self.board_widget.highlighted(cells);
+        self
+    }
 }
 
 impl<'a> Widget for &'a PuzzleBoardWidget<'a> {
@@ -219,7 +219,7 @@ impl<'a> FullBoardWidget<'a> {
                 Style::default()
                     .fg(Color::Green)
             );
-        Self { board_widget: board_widget }
+        Self { board_widget }
     }
 }
 
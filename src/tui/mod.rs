@@ -11,6 +11,9 @@ use custom_widgets::*;
 use crate::{
     generator::*,
     game::*,
+    smart_solver,
+    smart_solver::Hint,
+    solver::{ Budget, SolverKind },
 };
 
 use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
@@ -33,11 +36,16 @@ use ratatui::{
     text::{Line, Span},
     Terminal,
     prelude::Direction,
-    widgets::{Block, Borders, Paragraph, Widget},
+    widgets::{Paragraph, Widget, Wrap},
 };
 
 use color_eyre::eyre::WrapErr;
 
+// how long the generator may take before the app gives up on a puzzle
+const GENERATION_TIMEOUT: Duration = Duration::from_secs(3);
+// and how long working out a hint may take
+const HINT_TIMEOUT: Duration = Duration::from_secs(1);
+
 // --------------------------------------------------------------------
 
 #[derive(Debug, Default, PartialEq, Eq, Clone)]
@@ -79,6 +87,7 @@ pub enum Message {
     DeleteDigit,
     CheckSolution,
     ShowSolutionStatus(SolutionStatus),
+    ShowHint,
 }
 
 #[derive(Debug)]
@@ -88,18 +97,24 @@ pub struct App {
     solution: Option<Board>,
     running_state: RunningState,
     position: Option<(usize, usize)>,
-    solution_status: Option<bool>
+    solution_status: Option<bool>,
+    generation_failed: bool,
+    hint: Option<Hint>,
+    hint_failed: bool,
 }
 
 impl App {
     pub fn new(generator: NaiveGenerator) -> Self {
         Self {
-            generator: generator,
+            generator,
             puzzle: None,
             running_state: RunningState::InitialScreen,
             solution: None,
             position: None,
-            solution_status: None
+            solution_status: None,
+            generation_failed: false,
+            hint: None,
+            hint_failed: false,
         }
     }
 
@@ -134,6 +149,7 @@ impl App {
             KeyCode::Char('k') => Some(Message::Move(MoveDirection::Up)),
             KeyCode::Char('j') => Some(Message::Move(MoveDirection::Down)),
             KeyCode::Char('x') => Some(Message::DeleteDigit),
+            KeyCode::Char('?') => Some(Message::ShowHint),
             KeyCode::Enter => Some(Message::CheckSolution),
 
             KeyCode::Char(x) => {
@@ -170,23 +186,25 @@ impl App {
 
     /// Handle events and emit messages
     fn handle_event(&mut self) -> color_eyre::Result<Option<Message>> {
-        if event::poll(Duration::from_millis(250))? {
-            if let Event::Key(key) = event::read()? {
-                match key.kind {
-                    KeyEventKind::Press => return Ok(self.handle_key(key)),
-                    // KeyEventKind::Repeat => {
-                    //     if let KeyCode::Char('s') = key.code {
-                    //       return Ok(Some(Message::ShowSolution));
-                    //     }
-                    // },
-                    // KeyEventKind::Release => {
-                    //     if let KeyCode::Char('s') = key.code {
-                    //       return Ok(Some(Message::ShowSolution));
-                    //     }
-                    // },
-                    _ => {}
+        if event::poll(Duration::from_millis(250))?
+            && let Event::Key(key) = event::read()?
+        {
+            // a match, to keep room for the arms below
+            #[allow(clippy::single_match)]
+            match key.kind {
+                KeyEventKind::Press => return Ok(self.handle_key(key)),
+                // KeyEventKind::Repeat => {
+                //     if let KeyCode::Char('s') = key.code {
+                //       return Ok(Some(Message::ShowSolution));
+                //     }
+                // },
+                // KeyEventKind::Release => {
+                //     if let KeyCode::Char('s') = key.code {
+                //       return Ok(Some(Message::ShowSolution));
+                //     }
+                // },
+                _ => {}
 
-                }
             }
         }
         Ok(None)
@@ -297,36 +315,42 @@ impl App {
                 return Some(Message::GenerateNewPuzzle);
             },
             Message::GenerateNewPuzzle => {
-                let new_puzzle = self.generator.generate_puzzle();
-                self.solution = Some(new_puzzle);
-
-                let puzzle = new_puzzle.clone().non_givens_cleared();
-                self.puzzle = Some(puzzle);
+                let budget = Budget::unlimited().with_timeout(GENERATION_TIMEOUT);
+                match self.generator.generate_puzzle_within(&budget) {
+                    Ok(new_puzzle) => {
+                        self.solution = Some(new_puzzle);
+
+                        let puzzle = new_puzzle.non_givens_cleared();
+                        self.puzzle = Some(puzzle);
+                        self.generation_failed = false;
+                    },
+                    // keep the previous puzzle, if any
+                    Err(_) => self.generation_failed = true,
+                }
 
                 self.running_state = RunningState::PuzzleScreen(PuzzleScreenState::Puzzle);
 
                 self.solution_status = None;
+                self.hint = None;
+                self.hint_failed = false;
             },
             Message::Quit => {
                 self.running_state = RunningState::Done;
             },
             Message::ShowSolution => {
-                match &self.running_state {
-                    RunningState::PuzzleScreen(x) => {
-                        match x {
-                            PuzzleScreenState::Puzzle => {
-                                self.running_state = RunningState::PuzzleScreen(
-                                    PuzzleScreenState::Solution
-                                );
-                            },
-                            PuzzleScreenState::Solution => {
-                                self.running_state = RunningState::PuzzleScreen(
-                                    PuzzleScreenState::Puzzle
-                                );
-                            },
-                        }
+                if let RunningState::PuzzleScreen(x) = &self.running_state {
+                    match x {
+                        PuzzleScreenState::Puzzle => {
+                            self.running_state = RunningState::PuzzleScreen(
+                                PuzzleScreenState::Solution
+                            );
+                        },
+                        PuzzleScreenState::Solution => {
+                            self.running_state = RunningState::PuzzleScreen(
+                                PuzzleScreenState::Puzzle
+                            );
+                        },
                     }
-                    _ => {},
                 };
             },
             Message::Move(direction) => {
@@ -339,9 +363,13 @@ impl App {
             },
             Message::EnterDigit(digit) => {
                 self.enter_digit(digit);
+                self.hint = None;
+                self.hint_failed = false;
             },
             Message::DeleteDigit => {
                 self.delete_non_given();
+                self.hint = None;
+                self.hint_failed = false;
             },
             Message::CheckSolution => {
                 if self.valid_solution() {
@@ -360,6 +388,20 @@ impl App {
                     SolutionStatus::NotValid => self.solution_status = Some(false),
                 }
             },
+            Message::ShowHint => {
+                let Some(puzzle) = &self.puzzle else { return None; };
+                let budget = Budget::unlimited().with_timeout(HINT_TIMEOUT);
+                match smart_solver::hint_within(puzzle, &budget) {
+                    Ok(hint) => {
+                        self.hint = Some(hint);
+                        self.hint_failed = false;
+                    },
+                    Err(_) => {
+                        self.hint = None;
+                        self.hint_failed = true;
+                    },
+                }
+            },
         }
 
         None
@@ -374,6 +416,7 @@ impl App {
             " Set givens ".into(), "<+/->".blue().bold(),
             " Move accross the board ".into(), "<↑→↓←> ".blue().bold(),
             " Reset ".into(), "<r>".blue().bold(),
+            " Hint ".into(), "<?>".blue().bold(),
             " Show solution ".into(), "<s> ".blue().bold(),
             " Quit ".into(), "<q> ".blue().bold(),
         ]);
@@ -384,7 +427,7 @@ impl App {
         let screen_layout = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
-                Constraint::Max(4),
+                Constraint::Max(6),
                 Constraint::Min(13)
             ])
             .split(area);
@@ -394,24 +437,40 @@ impl App {
             Line::from(vec![Span::raw(format!("{:?}", self.generator))])
         );
 
+        if self.generation_failed {
+            header_lines.push(
+                Line::from(vec![Span::raw(format!(
+                    "Could not generate a puzzle with {} givens in time.",
+                    self.generator.num_givens
+                ))])
+            );
+        }
+
         match self.solution_status {
             None => {},
             Some(true) => {
                 header_lines.push(
                     Line::from(vec![Span::raw(
-                        format!("Solution is valid!")
+                        "Solution is valid!".to_string()
                     )])
                 );
             },
             Some(false) => {
                 header_lines.push(
                     Line::from(vec![Span::raw(
-                        format!("Solution is not valid!")
+                        "Solution is not valid!".to_string()
                     )])
                 );
             },
         }
 
+        if self.hint_failed {
+            header_lines.push(Line::from(vec![Span::raw("No hint in time.")]));
+        }
+        if let Some(hint) = &self.hint {
+            header_lines.push(Line::from(vec![Span::raw(hint.summary())]));
+        }
+
         // if self.position.is_some() {
         //     header_lines.push(
         //         Line::from(vec![Span::raw(
@@ -424,23 +483,27 @@ impl App {
         // }
 
 
-        Paragraph::new(header_lines).render(screen_layout[0], buf);
+        Paragraph::new(header_lines)
+            .wrap(Wrap { trim: true })
+            .render(screen_layout[0], buf);
 
 
         if self.puzzle.is_none() { return; }
 
         match self.running_state {
             RunningState::PuzzleScreen(PuzzleScreenState::Puzzle) =>
-                PuzzleBoardWidget::new(&self.puzzle.as_ref().unwrap())
-                    .set_position(self.position.clone())
+                PuzzleBoardWidget::new(self.puzzle.as_ref().unwrap())
+                    .set_position(self.position)
+                    .set_highlighted(self.hint.as_ref().map_or(&[], Hint::focus))
                     .render(screen_layout[1], buf),
             RunningState::PuzzleScreen(PuzzleScreenState::Solution) =>
-                FullBoardWidget::new(&self.solution.as_ref().unwrap())
+                FullBoardWidget::new(self.solution.as_ref().unwrap())
                     .render(screen_layout[1], buf),
             _ => {},
         }
     }
 
+    #[allow(clippy::vec_init_then_push)]
     fn render_initial_screen(&self, area: Rect, buf: &mut Buffer) {
         let title = Line::from(" ku - The ultimate sudoku app, eventually ".bold());
 
@@ -499,12 +562,12 @@ pub fn install_panic_hook() {
     }));
 }
 
-pub fn run() -> color_eyre::Result<()> {
+pub fn run(solver: SolverKind) -> color_eyre::Result<()> {
     install_panic_hook();
 
     // setting up the terminal
     let mut terminal = init_terminal()?;
-    let mut app = App::new(NaiveGenerator::new(0));
+    let mut app = App::new(NaiveGenerator::new(0).with_solver(solver));
 
     let app_result = app.run(&mut terminal).wrap_err("run failed");
     if let Err(err) = restore_terminal() {
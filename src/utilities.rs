@@ -13,11 +13,35 @@ pub fn square_limits_from_cell(r: usize, c: usize) -> (usize, usize) {
     (u, l)
 }
 
+// the square numbering is drawn below
+pub fn square_from_cell(r: usize, c: usize) -> usize {
+    (r / 3) * 3 + c / 3
+}
+
 pub fn coords_from_pos(pos: usize) -> (usize, usize) {
     let r = pos / 9; let c = pos % 9;
     (r, c)
 }
 
+// the 20 cells sharing a row, column or square with (r, c)
+pub fn peers(r: usize, c: usize) -> impl Iterator<Item = (usize, usize)> {
+    let (u, l) = square_limits_from_cell(r, c);
+    (0..9).filter(move |&j| j != c).map(move |j| (r, j))
+        .chain((0..9).filter(move |&i| i != r).map(move |i| (i, c)))
+        .chain((0..9).map(move |k| (u + k / 3, l + k % 3))
+            .filter(move |&(i, j)| i != r && j != c))
+}
+
+// whether two different cells share a row, column or square
+pub fn are_peers((r, c): (usize, usize), (i, j): (usize, usize)) -> bool {
+    (r, c) != (i, j) && (r == i || c == j || (r / 3 == i / 3 && c / 3 == j / 3))
+}
+
+// the usual 1-based "r5c3" notation
+pub fn cell_name(r: usize, c: usize) -> String {
+    format!("r{}c{}", r + 1, c + 1)
+}
+
 // i -> r; j -> c
 //  0 1 2 3 4 5 6 7 8
 // 0     |     |